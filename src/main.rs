@@ -1,17 +1,41 @@
-use iced_audio_player::scene::Scene;
+use iced_audio_player::scene::{
+    Ballistics, Colormap, Scene, ScopeConfig, ScrollDirection,
+    SpectrogramConfig, SpectrumOverlays, SpectrumScale, SpectrumView,
+    Visualization,
+};
 
 use iced::alignment::Vertical;
-use iced::time::Instant;
-use iced::widget::{button, column, container, row, slider, text};
-use iced::{event, executor, Event};
+use iced::mouse::{self, ScrollDelta};
+use iced::time::{Duration, Instant};
+use iced::widget::{
+    button, checkbox, column, container, mouse_area, pick_list, row, slider,
+    text, Canvas,
+};
+use iced::{event, executor, subscription, Event};
 use iced::{theme, window};
 use iced::{
     Alignment, Application, Command, Element, Length, Subscription, Theme,
 };
 use iced_audio_player::icon::Icon;
 use iced_audio_player::message::Message;
-use iced_audio_player::player::Player;
-use iced_audio_player::widget::CircleButtonStyle;
+use iced_audio_player::player::backend::{
+    CpalBackend, DeviceId, NullBackend, OutputBackend,
+};
+use iced_audio_player::player::loudness::{
+    Loudness, LoudnessReport, ReportFormat,
+};
+use iced_audio_player::player::{
+    AnalysisConfig, ChannelLevels, ChannelMode, NormalizationConfig, Player,
+    PlayerError, PlayerEvent, WindowFunction, MAX_VOLUME, MIN_VOLUME,
+};
+use iced_audio_player::widget::{CircleButtonStyle, LevelMeter};
+use std::path::PathBuf;
+
+/// Volume change in dB per line scrolled over the volume slider
+const VOLUME_STEP: f32 = 1.;
+
+/// Pixels of a touchpad scroll that count as a line
+const PIXELS_PER_LINE: f32 = 20.;
 
 fn main() -> iced::Result {
     AudioPlayer::run(iced::Settings {
@@ -24,10 +48,29 @@ fn main() -> iced::Result {
 struct AudioPlayer {
     last_updated: Instant,
     scene: Scene,
+    /// Spectrum scale as chosen, which the scene limits to the Nyquist
+    /// frequency of the output
+    scale: SpectrumScale,
     player: Player,
     seek_bar_value: f32,
     seek_bar_dragging: bool,
     duration: f32,
+    error: Option<PlayerError>,
+    devices: Vec<DeviceId>,
+    show_settings: bool,
+    levels: Vec<ChannelLevels>,
+    /// Set when the clip count of a channel grows, and cleared when the
+    /// meters are clicked
+    clipped: Vec<bool>,
+    show_loudness: bool,
+    loudness: Loudness,
+    /// Loudness of the whole loaded file, once it was scanned
+    loudness_report: Option<LoudnessReport>,
+    /// Where the last loudness report was written
+    exported_report: Option<PathBuf>,
+    /// Whether the mouse is over the volume controls, so scrolling adjusts
+    /// the volume
+    volume_hovered: bool,
 }
 
 impl Application for AudioPlayer {
@@ -37,14 +80,34 @@ impl Application for AudioPlayer {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let backend: Box<dyn OutputBackend> =
+            match CpalBackend::default_output() {
+                Ok(backend) => Box::new(backend),
+                Err(err) => {
+                    log::warn!("{}, audio is discarded", err);
+                    Box::new(NullBackend)
+                }
+            };
+
         (
             Self {
                 last_updated: Instant::now(),
                 scene: Scene::new(),
-                player: Player::new(),
+                scale: SpectrumScale::default(),
+                player: Player::new(backend),
                 seek_bar_value: 0f32,
                 seek_bar_dragging: false,
                 duration: 0f32,
+                error: None,
+                devices: list_devices(),
+                show_settings: false,
+                levels: vec![],
+                clipped: vec![],
+                show_loudness: false,
+                loudness: Loudness::empty(),
+                loudness_report: None,
+                exported_report: None,
+                volume_hovered: false,
             },
             Command::none(),
         )
@@ -57,25 +120,71 @@ impl Application for AudioPlayer {
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
             Message::Tick(time) => {
+                if let Some(err) = self.player.poll_error() {
+                    self.error = Some(err);
+                }
                 if !self.seek_bar_dragging {
                     self.seek_bar_value = self.player.get_position();
                 }
-                self.scene.update_spectrum(
-                    &self.player.get_fft_spectrum(),
-                    time - self.last_updated,
-                );
+                let dt = time - self.last_updated;
+                // The output's sample rate changes with the loaded file
+                self.update_scale();
+                self.scene
+                    .update_spectrum(self.player.get_fft_spectrum(), dt);
+                self.scene.update_waveform(self.player.get_waveform(), dt);
+
+                // The clip counts start over when the file is opened again
+                let levels = &self.player.get_levels().channels;
+                self.clipped.resize(levels.len(), false);
+                for ((clipped, previous), current) in
+                    self.clipped.iter_mut().zip(&self.levels).zip(levels)
+                {
+                    *clipped |= current.clips > previous.clips;
+                }
+                self.levels.clone_from(levels);
+                self.loudness = self.player.get_levels().loudness;
                 self.last_updated = time;
             }
             Message::Play => {
-                self.player.play();
+                if let Err(err) = self.player.play() {
+                    self.error = Some(err);
+                }
             }
             Message::Pause => {
-                self.player.pause();
+                if let Err(err) = self.player.pause() {
+                    self.error = Some(err);
+                }
+            }
+            Message::SetVolume(volume) => {
+                self.player.set_volume(volume);
             }
-            Message::LoadFile(path) => {
-                self.player.load_file(path);
-                self.duration = self.player.get_duration();
+            Message::ToggleMute => {
+                self.player.set_muted(!self.player.is_muted());
             }
+            Message::VolumeHovered(hovered) => {
+                self.volume_hovered = hovered;
+            }
+            Message::VolumeScrolled(delta) => {
+                if self.volume_hovered {
+                    let lines = match delta {
+                        ScrollDelta::Lines { y, .. } => y,
+                        ScrollDelta::Pixels { y, .. } => y / PIXELS_PER_LINE,
+                    };
+                    self.player.set_volume(
+                        self.player.get_volume() + lines * VOLUME_STEP,
+                    );
+                }
+            }
+            Message::LoadFile(path) => match self.player.load_file(path) {
+                Ok(()) => {
+                    self.duration = self.player.get_duration();
+                    self.error = None;
+                    // Files scanned before don't need another scan
+                    self.loudness_report = self.player.get_loudness_report();
+                    self.exported_report = None;
+                }
+                Err(err) => self.error = Some(err),
+            },
             Message::SetPositionPreview(position) => {
                 self.seek_bar_dragging = true;
                 self.seek_bar_value = position;
@@ -84,6 +193,113 @@ impl Application for AudioPlayer {
                 self.player.set_position(self.seek_bar_value);
                 self.seek_bar_dragging = false;
             }
+            Message::DismissError => {
+                self.error = None;
+            }
+            Message::SetChannelMode(mode) => {
+                if let Err(err) = self.player.set_channel_mode(mode) {
+                    self.error = Some(err);
+                }
+            }
+            Message::SelectDevice(id) => {
+                if let Err(err) = self.player.set_output_device(&id) {
+                    self.error = Some(err);
+                }
+            }
+            Message::RefreshDevices => {
+                self.devices = list_devices();
+            }
+            Message::PlayerEvent(event) => match event {
+                PlayerEvent::TrackEnded => {
+                    if let Err(err) = self.player.stop() {
+                        self.error = Some(err);
+                    }
+                }
+                PlayerEvent::Loaded { .. } => {
+                    self.duration = self.player.get_duration();
+                }
+                PlayerEvent::BufferUnderrun => {
+                    log::warn!("Output ran out of decoded audio");
+                }
+                PlayerEvent::LoudnessScanned(report) => {
+                    self.loudness_report = Some(report);
+                }
+                PlayerEvent::ScanFailed(err) => self.error = Some(err),
+                PlayerEvent::PositionChanged { .. }
+                | PlayerEvent::StreamError(_) => {}
+            },
+            Message::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+            }
+            Message::ToggleLoudness => {
+                self.show_loudness = !self.show_loudness;
+            }
+            Message::ResetLoudness => {
+                self.player.reset_loudness();
+            }
+            Message::ScanLoudness => {
+                self.player.scan_loudness();
+            }
+            Message::ExportLoudnessReport(format) => {
+                if let Some(ref report) = self.loudness_report {
+                    match report.export(format) {
+                        Ok(path) => self.exported_report = Some(path),
+                        Err(err) => self.error = Some(err),
+                    }
+                }
+            }
+            Message::SetNormalization(config) => {
+                self.player.set_normalization(config);
+            }
+            Message::SetFftSize(fft_size) => {
+                self.player.set_analysis_config(AnalysisConfig {
+                    fft_size,
+                    ..self.player.get_analysis_config()
+                });
+            }
+            Message::SetWindowFunction(window) => {
+                self.player.set_analysis_config(AnalysisConfig {
+                    window,
+                    ..self.player.get_analysis_config()
+                });
+            }
+            Message::SetOverlap(overlap) => {
+                self.player.set_analysis_config(AnalysisConfig {
+                    overlap,
+                    ..self.player.get_analysis_config()
+                });
+            }
+            Message::SetVisualization(visualization) => {
+                self.scene.set_visualization(visualization);
+            }
+            Message::SetRmsTime(rms_time) => {
+                self.player.set_analysis_config(AnalysisConfig {
+                    rms_time,
+                    ..self.player.get_analysis_config()
+                });
+            }
+            Message::ResetClipIndicators => {
+                self.clipped.fill(false);
+            }
+            Message::SetSpectrumView(view) => {
+                self.scene.set_view(view);
+            }
+            Message::SetSpectrumScale(scale) => {
+                self.scale = scale;
+                self.update_scale();
+            }
+            Message::SetBallistics(ballistics) => {
+                self.scene.set_ballistics(ballistics);
+            }
+            Message::SetSpectrumOverlays(overlays) => {
+                self.scene.set_overlays(overlays);
+            }
+            Message::SetSpectrogramConfig(config) => {
+                self.scene.set_spectrogram_config(config);
+            }
+            Message::SetScopeConfig(config) => {
+                self.scene.set_scope_config(config);
+            }
         }
 
         Command::none()
@@ -105,6 +321,19 @@ impl Application for AudioPlayer {
             .into()
         };
 
+        let canvas = if self.player.is_streaming() {
+            let meter = Canvas::new(LevelMeter::new(
+                &self.levels,
+                &self.clipped,
+                Message::ResetClipIndicators,
+            ))
+            .width(90)
+            .height(Length::Fill);
+            row![canvas, meter].padding([0, 10]).spacing(10).into()
+        } else {
+            canvas
+        };
+
         let mut play_btn = if !self.player.is_streaming() {
             button(Icon::PLAY.into_element())
         } else if self.player.is_playing() {
@@ -119,6 +348,31 @@ impl Application for AudioPlayer {
                     CircleButtonStyle::new(theme::Button::Primary),
                 )));
 
+        let volume = self.player.get_volume();
+        let mute_btn = button(if self.player.is_muted() {
+            "Unmute"
+        } else {
+            "Mute"
+        })
+        .on_press(Message::ToggleMute);
+        let volume_slider =
+            slider(MIN_VOLUME..=MAX_VOLUME, volume, Message::SetVolume)
+                .step(0.5)
+                .width(100);
+        let volume_label = text(if volume <= MIN_VOLUME {
+            "-inf dB".to_string()
+        } else {
+            format!("{:.1} dB", volume)
+        })
+        .width(60);
+        let volume_controls = mouse_area(
+            row![mute_btn, volume_slider, volume_label]
+                .align_items(Alignment::Center)
+                .spacing(10),
+        )
+        .on_enter(Message::VolumeHovered(true))
+        .on_exit(Message::VolumeHovered(false));
+
         let seek_bar = slider(
             0f32..=self.duration,
             self.seek_bar_value,
@@ -126,11 +380,53 @@ impl Application for AudioPlayer {
         )
         .on_release(Message::SetPosition);
 
+        let status = text(if self.player.is_recovering() {
+            "Waiting for an output device..."
+        } else {
+            ""
+        });
+
         let time_played_label =
             text(seconds_to_minutes(self.seek_bar_value)).width(35);
         let duration_label = text(seconds_to_minutes(self.duration)).width(35);
 
-        let top_controls = row![play_btn].spacing(10);
+        let channel_mode = pick_list(
+            &ChannelMode::ALL[..],
+            Some(self.player.get_channel_mode()),
+            Message::SetChannelMode,
+        );
+
+        let device = pick_list(
+            self.devices.as_slice(),
+            self.player.get_output_device(),
+            Message::SelectDevice,
+        )
+        .placeholder("No output device");
+        let refresh_devices =
+            button("Refresh").on_press(Message::RefreshDevices);
+
+        let visualization = pick_list(
+            &Visualization::ALL[..],
+            Some(self.scene.get_visualization()),
+            Message::SetVisualization,
+        );
+
+        let settings_btn = button("Settings").on_press(Message::ToggleSettings);
+        let loudness_btn = button("Loudness").on_press(Message::ToggleLoudness);
+
+        let top_controls = row![
+            play_btn,
+            volume_controls,
+            channel_mode,
+            device,
+            refresh_devices,
+            visualization,
+            settings_btn,
+            loudness_btn,
+            status
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
 
         let bottom_controls =
             row![time_played_label, seek_bar, duration_label].spacing(10);
@@ -140,7 +436,29 @@ impl Application for AudioPlayer {
             .padding(10)
             .spacing(10);
 
-        container(column![canvas, controls].align_items(Alignment::Center))
+        let mut content = column![].align_items(Alignment::Center);
+        if let Some(ref err) = self.error {
+            content = content.push(
+                row![
+                    text(err.to_string()).width(Length::Fill),
+                    button("Dismiss")
+                        .on_press(Message::DismissError)
+                        .style(theme::Button::Destructive),
+                ]
+                .align_items(Alignment::Center)
+                .padding(10)
+                .spacing(10),
+            );
+        }
+        if self.show_settings {
+            content = content.push(self.settings());
+        }
+        if self.show_loudness {
+            content = content.push(self.loudness_panel());
+        }
+        let content = content.push(canvas).push(controls);
+
+        container(content)
             .width(Length::Fill)
             .height(Length::Fill)
             .center_x()
@@ -160,13 +478,409 @@ impl Application for AudioPlayer {
             Event::Window(_, window::Event::FileDropped(path)) => {
                 Some(Message::LoadFile(path))
             }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                Some(Message::VolumeScrolled(delta))
+            }
             _ => None,
         }));
 
+        subscriptions.push(subscription::unfold(
+            "player-events",
+            self.player.events(),
+            |events| async move {
+                match events.next().await {
+                    Some(event) => (Message::PlayerEvent(event), events),
+                    None => iced::futures::future::pending().await,
+                }
+            },
+        ));
+
         Subscription::batch(subscriptions)
     }
 }
 
+impl AudioPlayer {
+    /// Highest frequency in Hz the spectrum can show at the output's sample
+    /// rate
+    fn nyquist_frequency(&self) -> f32 {
+        self.player.get_output_sample_rate() as f32 / 2.
+    }
+
+    /// Shows the chosen scale, up to the highest frequency the output can
+    /// carry
+    fn update_scale(&mut self) {
+        let scale = SpectrumScale {
+            max_frequency: self
+                .scale
+                .max_frequency
+                .min(self.nyquist_frequency()),
+            ..self.scale
+        };
+        if scale != self.scene.get_scale() {
+            self.scene.set_scale(scale);
+        }
+    }
+
+    fn loudness_panel(&self) -> Element<'_, Message> {
+        let loudness = self.loudness;
+        // Measured before the normalization and the volume
+        let live = row![
+            text("Live, before gain"),
+            text(format!("Momentary {:.1} LUFS", loudness.momentary)),
+            text(format!("Short-term {:.1} LUFS", loudness.short_term)),
+            text(format!("Integrated {:.1} LUFS", loudness.integrated)),
+            text(format!("Range {:.1} LU", loudness.range)),
+            button("Reset").on_press(Message::ResetLoudness),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(20);
+
+        let scanning = self.player.is_scanning_loudness();
+        let scan_btn =
+            button(if scanning { "Scanning..." } else { "Scan file" })
+                .on_press_maybe(
+                    (!scanning && self.player.get_path().is_some())
+                        .then_some(Message::ScanLoudness),
+                );
+        let mut file =
+            row![scan_btn].align_items(Alignment::Center).spacing(20);
+        if let Some(ref report) = self.loudness_report {
+            file = file
+                .push(text(format!(
+                    "File: integrated {:.1} LUFS, range {:.1} LU, \
+                     max momentary {:.1} LUFS, max short-term {:.1} LUFS, \
+                     true peak {:.1} dBTP",
+                    report.integrated,
+                    report.range,
+                    report.max_momentary,
+                    report.max_short_term,
+                    report.true_peak
+                )))
+                .push(button("Export text").on_press(
+                    Message::ExportLoudnessReport(ReportFormat::Text),
+                ))
+                .push(button("Export JSON").on_press(
+                    Message::ExportLoudnessReport(ReportFormat::Json),
+                ));
+        }
+        if let Some(ref path) = self.exported_report {
+            file = file.push(text(format!("Saved {}", path.display())));
+        }
+
+        let normalization = self.player.get_normalization();
+        let enabled = checkbox("Normalize to", normalization.enabled)
+            .on_toggle(move |enabled| {
+                Message::SetNormalization(NormalizationConfig {
+                    enabled,
+                    ..normalization
+                })
+            });
+        let target = slider(
+            NormalizationConfig::MIN_TARGET..=NormalizationConfig::MAX_TARGET,
+            normalization.target,
+            move |target| {
+                Message::SetNormalization(NormalizationConfig {
+                    target,
+                    ..normalization
+                })
+            },
+        )
+        .step(1.)
+        .width(150);
+        let gain = match self.player.get_normalization_gain() {
+            Some(gain) => format!("Gain {:+.1} dB", gain),
+            None if self.player.is_scanning_loudness() => {
+                "Scanning...".to_string()
+            }
+            None => String::new(),
+        };
+        let normalization = row![
+            enabled,
+            text(format!("{} LUFS", normalization.target)),
+            target,
+            text(gain),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        column![live, file, normalization]
+            .padding(10)
+            .spacing(10)
+            .into()
+    }
+
+    fn settings(&self) -> Element<'_, Message> {
+        let config = self.player.get_analysis_config();
+
+        let fft_size = pick_list(
+            &AnalysisConfig::FFT_SIZES[..],
+            Some(config.fft_size),
+            Message::SetFftSize,
+        );
+        let window = pick_list(
+            &WindowFunction::ALL[..],
+            Some(config.window),
+            Message::SetWindowFunction,
+        );
+        let overlap = slider(0f32..=0.9, config.overlap, Message::SetOverlap)
+            .step(0.05)
+            .width(150);
+
+        let rms_time = slider(
+            10f32..=3000.,
+            config.rms_time.as_secs_f32() * 1000.,
+            |rms_time| {
+                Message::SetRmsTime(Duration::from_secs_f32(rms_time / 1000.))
+            },
+        )
+        .step(10.)
+        .width(100);
+
+        let spectrum_view = pick_list(
+            &SpectrumView::ALL[..],
+            Some(self.scene.get_view()),
+            Message::SetSpectrumView,
+        );
+
+        let analysis = row![
+            text("Show"),
+            spectrum_view,
+            text("FFT size"),
+            fft_size,
+            text("Window"),
+            window,
+            text(format!("Overlap {:.0}%", config.overlap * 100.)),
+            overlap,
+            text(format!(
+                "RMS {:.0} ms",
+                config.rms_time.as_secs_f32() * 1000.
+            )),
+            rms_time,
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let scale = self.scale;
+        // Limited to the Nyquist frequency of the output
+        let shown = self.scene.get_scale();
+        let floor = slider(-160f32..=-30., scale.floor, move |floor| {
+            Message::SetSpectrumScale(SpectrumScale { floor, ..scale })
+        })
+        .step(5.)
+        .width(100);
+        let ceiling = slider(-20f32..=20., scale.ceiling, move |ceiling| {
+            Message::SetSpectrumScale(SpectrumScale { ceiling, ..scale })
+        })
+        .step(1.)
+        .width(100);
+        let min_frequency =
+            slider(10f32..=1000., scale.min_frequency, move |frequency| {
+                Message::SetSpectrumScale(SpectrumScale {
+                    min_frequency: frequency,
+                    ..scale
+                })
+            })
+            .step(10.)
+            .width(100);
+        let max_frequency = slider(
+            2000f32..=self.nyquist_frequency(),
+            shown.max_frequency,
+            move |frequency| {
+                Message::SetSpectrumScale(SpectrumScale {
+                    max_frequency: frequency,
+                    ..scale
+                })
+            },
+        )
+        .step(500.)
+        .width(100);
+
+        let display = row![
+            text(format!("Floor {} dB", scale.floor)),
+            floor,
+            text(format!("Ceiling {} dB", scale.ceiling)),
+            ceiling,
+            text(format!("From {} Hz", scale.min_frequency)),
+            min_frequency,
+            text(format!("To {} Hz", shown.max_frequency)),
+            max_frequency,
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let ballistics = self.scene.get_ballistics();
+        let attack = slider(
+            0f32..=500.,
+            ballistics.attack.as_secs_f32() * 1000.,
+            move |attack| {
+                Message::SetBallistics(Ballistics {
+                    attack: Duration::from_secs_f32(attack / 1000.),
+                    ..ballistics
+                })
+            },
+        )
+        .step(10.)
+        .width(100);
+        let release = slider(
+            0f32..=2000.,
+            ballistics.release.as_secs_f32() * 1000.,
+            move |release| {
+                Message::SetBallistics(Ballistics {
+                    release: Duration::from_secs_f32(release / 1000.),
+                    ..ballistics
+                })
+            },
+        )
+        .step(50.)
+        .width(100);
+        let hold =
+            slider(0f32..=5., ballistics.hold.as_secs_f32(), move |hold| {
+                Message::SetBallistics(Ballistics {
+                    hold: Duration::from_secs_f32(hold),
+                    ..ballistics
+                })
+            })
+            .step(0.25)
+            .width(100);
+        let decay = slider(1f32..=60., ballistics.decay, move |decay| {
+            Message::SetBallistics(Ballistics {
+                decay,
+                ..ballistics
+            })
+        })
+        .step(1.)
+        .width(100);
+        let average = slider(
+            1f32..=60.,
+            ballistics.average.as_secs_f32(),
+            move |average| {
+                Message::SetBallistics(Ballistics {
+                    average: Duration::from_secs_f32(average),
+                    ..ballistics
+                })
+            },
+        )
+        .step(1.)
+        .width(100);
+
+        let overlays = self.scene.get_overlays();
+        let show_peak_hold = checkbox("Peak hold", overlays.peak_hold)
+            .on_toggle(move |peak_hold| {
+                Message::SetSpectrumOverlays(SpectrumOverlays {
+                    peak_hold,
+                    ..overlays
+                })
+            });
+        let show_average =
+            checkbox("Average", overlays.average).on_toggle(move |average| {
+                Message::SetSpectrumOverlays(SpectrumOverlays {
+                    average,
+                    ..overlays
+                })
+            });
+
+        let traces = row![
+            text(format!(
+                "Attack {:.0} ms",
+                ballistics.attack.as_secs_f32() * 1000.
+            )),
+            attack,
+            text(format!(
+                "Release {:.0} ms",
+                ballistics.release.as_secs_f32() * 1000.
+            )),
+            release,
+            show_peak_hold,
+            text(format!("Hold {:.2} s", ballistics.hold.as_secs_f32())),
+            hold,
+            text(format!("Decay {} dB/s", ballistics.decay)),
+            decay,
+            show_average,
+            text(format!("Over {:.0} s", ballistics.average.as_secs_f32())),
+            average,
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let spectrogram = self.scene.get_spectrogram_config();
+        let colormap = pick_list(
+            &Colormap::ALL[..],
+            Some(spectrogram.colormap),
+            move |colormap| {
+                Message::SetSpectrogramConfig(SpectrogramConfig {
+                    colormap,
+                    ..spectrogram
+                })
+            },
+        );
+        let direction = pick_list(
+            &ScrollDirection::ALL[..],
+            Some(spectrogram.direction),
+            move |direction| {
+                Message::SetSpectrogramConfig(SpectrogramConfig {
+                    direction,
+                    ..spectrogram
+                })
+            },
+        );
+        let span = pick_list(
+            &SpectrogramConfig::SPANS[..],
+            Some(spectrogram.span),
+            move |span| {
+                Message::SetSpectrogramConfig(SpectrogramConfig {
+                    span,
+                    ..spectrogram
+                })
+            },
+        );
+
+        let spectrogram = row![
+            text("Spectrogram colors"),
+            colormap,
+            text("Scroll"),
+            direction,
+            text("Seconds"),
+            span,
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        let scope = self.scene.get_scope_config();
+        let scope_window = slider(1f32..=100., scope.window, move |window| {
+            Message::SetScopeConfig(ScopeConfig { window, ..scope })
+        })
+        .step(1.)
+        .width(100);
+        let scope_gain = slider(1f32..=20., scope.gain, move |gain| {
+            Message::SetScopeConfig(ScopeConfig { gain, ..scope })
+        })
+        .step(0.5)
+        .width(100);
+
+        let scope = row![
+            text(format!("Scope window {} ms", scope.window)),
+            scope_window,
+            text(format!("Gain x{}", scope.gain)),
+            scope_gain,
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
+
+        column![analysis, display, traces, spectrogram, scope]
+            .padding(10)
+            .spacing(10)
+            .into()
+    }
+}
+
+fn list_devices() -> Vec<DeviceId> {
+    Player::list_output_devices()
+        .into_iter()
+        .flat_map(|host| host.devices)
+        .map(|device| device.id)
+        .collect()
+}
+
 fn seconds_to_minutes(seconds: f32) -> String {
     let minutes = seconds as u32 / 60;
     let seconds_left = seconds as u32 % 60;
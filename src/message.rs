@@ -1,12 +1,49 @@
+use crate::player::backend::DeviceId;
+use crate::player::loudness::ReportFormat;
+use crate::player::{
+    ChannelMode, NormalizationConfig, PlayerEvent, WindowFunction,
+};
+use crate::scene::{
+    Ballistics, ScopeConfig, SpectrogramConfig, SpectrumOverlays,
+    SpectrumScale, SpectrumView, Visualization,
+};
+use iced::mouse::ScrollDelta;
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Tick(Instant),
     Play,
     Pause,
+    SetVolume(f32),
+    ToggleMute,
+    VolumeHovered(bool),
+    VolumeScrolled(ScrollDelta),
     LoadFile(PathBuf),
     SetPositionPreview(f32),
     SetPosition,
+    DismissError,
+    SetChannelMode(ChannelMode),
+    SelectDevice(DeviceId),
+    RefreshDevices,
+    PlayerEvent(PlayerEvent),
+    ToggleSettings,
+    ToggleLoudness,
+    ResetLoudness,
+    ScanLoudness,
+    ExportLoudnessReport(ReportFormat),
+    SetNormalization(NormalizationConfig),
+    SetFftSize(usize),
+    SetWindowFunction(WindowFunction),
+    SetOverlap(f32),
+    SetRmsTime(Duration),
+    ResetClipIndicators,
+    SetVisualization(Visualization),
+    SetSpectrumView(SpectrumView),
+    SetSpectrumScale(SpectrumScale),
+    SetBallistics(Ballistics),
+    SetSpectrumOverlays(SpectrumOverlays),
+    SetSpectrogramConfig(SpectrogramConfig),
+    SetScopeConfig(ScopeConfig),
 }
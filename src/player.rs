@@ -1,182 +1,578 @@
-use apodize::hamming_iter;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{ChannelCount, Device, Stream, SupportedStreamConfig};
-use hound::WavReader;
-use rtrb::{Consumer, Producer, RingBuffer};
-use rustfft::num_complex::Complex;
-use rustfft::{Fft, FftPlanner};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicU32, Ordering};
+pub mod analysis;
+pub mod backend;
+pub mod channels;
+pub mod decoder;
+mod decoder_thread;
+mod error;
+mod event;
+mod gain;
+pub mod loudness;
+mod normalization;
+mod output;
+pub mod resampler;
+mod transport;
+mod true_peak;
+mod worker;
+
+use analysis::AnalysisCommand;
+pub use analysis::{AnalysisConfig, WindowFunction, MIN_DBFS};
+use backend::{
+    CpalBackend, DeviceId, HostInfo, OutputBackend, OutputStream, StreamConfig,
+};
+use channels::ChannelMixer;
+pub use channels::ChannelMode;
+use cpal::ChannelCount;
+use decoder::Decoder;
+use decoder_thread::Source;
+pub use error::PlayerError;
+use event::EventSender;
+pub use event::{PlayerEvent, PlayerEvents};
+use futures::channel::mpsc::unbounded;
+use gain::Gain;
+use hound::WavSpec;
+use loudness::{Loudness, LoudnessReport};
+pub use normalization::NormalizationConfig;
+use normalization::Normalizer;
+use output::{Gains, Input, Output, Segment, Tap};
+pub use resampler::ResampleQuality;
+use resampler::Resampler;
+use rtrb::RingBuffer;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use transport::Transport;
+use triple_buffer::triple_buffer;
+use worker::Worker;
 
 pub const BUFFER_SIZE: usize = 4096;
 
-const MAX_FREQUENCY: f32 = 20000.;
+/// How often to look for an output device again after losing one
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Default length of the fade out and fade in around a seek
+const SEEK_FADE: Duration = Duration::from_millis(5);
+
+/// Lowest volume in dB, which is silence
+pub const MIN_VOLUME: f32 = -60.;
+
+/// Highest volume in dB. Volumes above unity would push the true peaks of
+/// normalized files past their ceiling.
+pub const MAX_VOLUME: f32 = 0.;
+
+/// Spectra of the audio being played, with the level in dBFS of every FFT
+/// bin from DC up to Nyquist
+#[derive(Clone)]
 pub struct FftSpectrum {
-    pub values: Vec<f32>,
+    /// Spectrum of every output channel
+    pub channels: Vec<Vec<f32>>,
+    /// Spectrum of the sum of the first two channels, `(L + R) / 2`
+    pub mid: Vec<f32>,
+    /// Spectrum of the difference of the first two channels, `(L - R) / 2`
+    pub side: Vec<f32>,
+    /// Frequency width of a bin in Hz
     pub bin_size: f32,
 }
 
 impl FftSpectrum {
     pub fn empty() -> Self {
         FftSpectrum {
-            values: vec![0f32; BUFFER_SIZE],
+            channels: vec![],
+            mid: vec![],
+            side: vec![],
             bin_size: 0f32,
         }
     }
+
+    pub fn left(&self) -> &[f32] {
+        self.channels.first().map_or(&[], Vec::as_slice)
+    }
+
+    /// The right channel, or the only channel of mono output
+    pub fn right(&self) -> &[f32] {
+        self.channels.get(1).map_or(self.left(), Vec::as_slice)
+    }
+}
+
+/// Most recent frames played, per channel and oldest first
+#[derive(Clone)]
+pub struct Waveform {
+    pub channels: Vec<Vec<f32>>,
+    pub sample_rate: u32,
+}
+
+impl Waveform {
+    pub fn empty() -> Self {
+        Waveform {
+            channels: vec![],
+            sample_rate: 0,
+        }
+    }
+}
+
+/// Readings of the level meters of one channel, in dBFS
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelLevels {
+    /// Highest sample, falling back after a peak
+    pub peak: f32,
+    /// Root mean square over the integration time of the analysis config
+    pub rms: f32,
+    /// Highest level between the samples as well, from 4 times oversampling
+    /// as in ITU-R BS.1770, falling back after a peak
+    pub true_peak: f32,
+    /// Highest true peak of the last seconds
+    pub peak_hold: f32,
+    /// Number of samples at or above full scale since the file was opened
+    pub clips: u64,
+}
+
+/// Level meters of every output channel and the loudness of their mix. Like
+/// the spectrum and the waveform they are measured before the loudness
+/// normalization and the volume, so they read the file as it is.
+#[derive(Clone)]
+pub struct Levels {
+    pub channels: Vec<ChannelLevels>,
+    /// Loudness of what was played since the file was opened or the
+    /// loudness was reset
+    pub loudness: Loudness,
+}
+
+impl Levels {
+    pub fn empty() -> Self {
+        Levels {
+            channels: vec![],
+            loudness: Loudness::empty(),
+        }
+    }
+}
+
+/// Tracks an output stream that failed and has to be rebuilt
+struct Recovery {
+    lost_device: Option<DeviceId>,
+    resume: bool,
+    last_attempt: Option<Instant>,
 }
 
 pub struct Player {
-    device: Device,
+    backend: Box<dyn OutputBackend>,
     sample_rate: cpal::SampleRate,
     channels: ChannelCount,
-    stream: Option<Stream>,
+    output_sample_rate: cpal::SampleRate,
+    resample_quality: ResampleQuality,
+    /// Mode of the loaded file
+    channel_mode: ChannelMode,
+    /// Modes chosen for files, the others play in `ChannelMode::Auto`
+    channel_modes: HashMap<PathBuf, ChannelMode>,
+    path: Option<PathBuf>,
+    stream: Option<Box<dyn OutputStream>>,
+    decoder_thread: Option<Worker>,
+    analysis_thread: Option<Worker>,
     is_playing: bool,
-    position: Arc<AtomicU32>,
-    duration: u32,
-    fft: Arc<dyn Fft<f32>>,
-    hamming_window: Vec<f32>,
-    output_len: usize,
-    fft_output: FftSpectrum,
-    buffer_consumer: Option<Consumer<f32>>,
+    transport: Arc<Transport>,
+    seek_fade: Duration,
+    /// Duration of the loaded file in frames
+    duration: u64,
+    /// Latest spectrum published by the analysis thread
+    spectrum: Option<triple_buffer::Output<FftSpectrum>>,
+    analysis_config: AnalysisConfig,
+    analysis_sender: Option<Sender<AnalysisCommand>>,
+    empty_spectrum: FftSpectrum,
+    /// Latest waveform published by the analysis thread
+    waveform: Option<triple_buffer::Output<Waveform>>,
+    empty_waveform: Waveform,
+    /// Latest levels published by the analysis thread
+    levels: Option<triple_buffer::Output<Levels>>,
+    empty_levels: Levels,
+    /// Gains of the loudness normalization and the volume, shared with
+    /// every stream
+    gains: Gains,
+    normalizer: Normalizer,
+    /// Volume in dB
+    volume: f32,
+    muted: bool,
+    /// Identifies the stream and decoder thread errors originate from, so
+    /// errors of replaced streams can be ignored
+    session: u64,
+    recovery: Option<Recovery>,
+    error_sender: Sender<(u64, PlayerError)>,
+    error_receiver: Receiver<(u64, PlayerError)>,
+    event_sender: EventSender,
+    events: PlayerEvents,
 }
 
 impl Player {
-    pub fn new() -> Self {
-        let mut fft_planner = FftPlanner::new();
-        let hamming_window: Vec<f32> = hamming_iter(BUFFER_SIZE)
-            .map(|f| f as f32)
-            .collect::<Vec<f32>>();
-
-        let host = cpal::default_host();
-
-        let device = host
-            .default_output_device()
-            .expect("no output device available");
+    pub fn new(backend: Box<dyn OutputBackend>) -> Self {
+        let (error_sender, error_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = unbounded();
+        let gains = Gains {
+            normalization: Arc::new(Gain::new(1.)),
+            volume: Arc::new(Gain::new(1.)),
+        };
 
         Self {
-            device,
+            backend,
             sample_rate: cpal::SampleRate(44100),
             channels: 2,
+            output_sample_rate: cpal::SampleRate(44100),
+            resample_quality: ResampleQuality::default(),
+            channel_mode: ChannelMode::default(),
+            channel_modes: HashMap::new(),
+            path: None,
             stream: None,
+            decoder_thread: None,
+            analysis_thread: None,
             is_playing: false,
-            position: Arc::new(AtomicU32::new(0)),
+            transport: Arc::new(Transport::new(0, 0)),
+            seek_fade: SEEK_FADE,
             duration: 0,
-            fft: fft_planner.plan_fft_forward(BUFFER_SIZE),
-            hamming_window,
-            output_len: BUFFER_SIZE,
-            fft_output: FftSpectrum::empty(),
-            buffer_consumer: None,
+            spectrum: None,
+            analysis_config: AnalysisConfig::default(),
+            analysis_sender: None,
+            empty_spectrum: FftSpectrum::empty(),
+            waveform: None,
+            empty_waveform: Waveform::empty(),
+            levels: None,
+            empty_levels: Levels::empty(),
+            normalizer: Normalizer::new(
+                gains.normalization.clone(),
+                event_sender.clone(),
+            ),
+            gains,
+            volume: 0.,
+            muted: false,
+            session: 0,
+            recovery: None,
+            error_sender,
+            error_receiver,
+            event_sender,
+            events: PlayerEvents::new(event_receiver),
+        }
+    }
+
+    /// Loads and plays `path`, in the channel mode last chosen for it
+    pub fn load_file(&mut self, path: PathBuf) -> Result<(), PlayerError> {
+        let mode = self.channel_modes.get(&path).copied().unwrap_or_default();
+        let previous = std::mem::replace(&mut self.channel_mode, mode);
+        let spec = match self.open(path, 0, true) {
+            Ok(spec) => spec,
+            Err(err) => {
+                self.channel_mode = previous;
+                return Err(err);
+            }
+        };
+        self.normalizer.load(self.path.as_deref());
+        let _ = self
+            .event_sender
+            .unbounded_send(PlayerEvent::Loaded { spec });
+        Ok(())
+    }
+
+    /// Events about the state of playback, see `PlayerEvent`
+    pub fn events(&self) -> PlayerEvents {
+        self.events.clone()
+    }
+
+    /// Sets how the loaded file is mixed to the output's channels. The mode
+    /// is remembered per file, since it depends on how the file was mixed,
+    /// and files it wasn't chosen for play in `ChannelMode::Auto`. The
+    /// previous mode is kept if that fails.
+    pub fn set_channel_mode(
+        &mut self,
+        mode: ChannelMode,
+    ) -> Result<(), PlayerError> {
+        let previous = std::mem::replace(&mut self.channel_mode, mode);
+        self.reopen(move |player| player.channel_mode = previous)?;
+        if let Some(path) = self.path.clone() {
+            self.channel_modes.insert(path, mode);
         }
+        Ok(())
     }
 
-    pub fn load_file(&mut self, path: PathBuf) {
-        let mut reader =
-            WavReader::open(path).expect("Failed to open wav file");
+    pub fn get_channel_mode(&self) -> ChannelMode {
+        self.channel_mode
+    }
 
-        let spec = reader.spec();
+    /// Lists the hosts and output devices audio can be played through
+    pub fn list_output_devices() -> Vec<HostInfo> {
+        CpalBackend::list_hosts()
+    }
 
-        let (mut input_producer, mut input_consumer) =
-            RingBuffer::new(BUFFER_SIZE * 3);
+    pub fn get_output_device(&self) -> Option<DeviceId> {
+        self.backend.device_id()
+    }
+
+    /// Switches to another output device, continuing the loaded file at the
+    /// current position
+    pub fn set_output_device(
+        &mut self,
+        id: &DeviceId,
+    ) -> Result<(), PlayerError> {
+        self.set_backend(Box::new(CpalBackend::open(id)?))
+    }
+
+    /// Switches to another output backend, continuing the loaded file at the
+    /// current position. The previous backend is kept if that fails.
+    pub fn set_backend(
+        &mut self,
+        backend: Box<dyn OutputBackend>,
+    ) -> Result<(), PlayerError> {
+        let previous = std::mem::replace(&mut self.backend, backend);
+        self.reopen(move |player| player.backend = previous)
+    }
+
+    /// Rebuilds the stream for the loaded file at the current position. If
+    /// that fails, `restore` undoes the change that needed the new stream
+    /// and the previous stream is rebuilt.
+    fn reopen(
+        &mut self,
+        restore: impl FnOnce(&mut Self),
+    ) -> Result<(), PlayerError> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let position = self.get_position_frames();
+        let playing = self.is_playing;
+
+        if let Err(err) = self.open(path.clone(), position, playing) {
+            restore(self);
+            let _ = self.open(path, position, playing);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Opens `path` at `start_frame` with a new stream. The previous stream
+    /// is stopped first, so it is gone even if building the new one fails.
+    fn open(
+        &mut self,
+        path: PathBuf,
+        start_frame: u64,
+        play: bool,
+    ) -> Result<WavSpec, PlayerError> {
+        let mut decoder = Decoder::open(&path)?;
+        let spec = decoder.spec();
+        let clip_level = decoder.full_scale();
+        if start_frame > 0 {
+            decoder.seek(start_frame)?;
+        }
+
+        let (input_producer, input_consumer) = RingBuffer::new(BUFFER_SIZE * 3);
+        let (tap_producer, tap_consumer) = RingBuffer::new(BUFFER_SIZE * 3);
+        let (segment_producer, segment_consumer) =
+            RingBuffer::<Segment>::new(BUFFER_SIZE / 16);
+
+        let layout = self.channel_mode.target_channels(spec.channels);
+        let config = self.backend.stream_config(StreamConfig {
+            sample_rate: spec.sample_rate,
+            channels: layout,
+        })?;
+        let mixer = ChannelMixer::new(spec.channels, layout, config.channels);
+        let resampler = if config.sample_rate != spec.sample_rate {
+            Some(Resampler::new(
+                self.resample_quality,
+                spec.sample_rate,
+                config.sample_rate,
+                config.channels as usize,
+            )?)
+        } else {
+            None
+        };
+
+        // Stop the previous stream and threads before building the new
+        // stream, since the device or the backend's output file may not be
+        // shared between two streams
+        self.stream = None;
+        self.decoder_thread = None;
+        self.analysis_thread = None;
+        self.is_playing = false;
+
+        let transport = Arc::new(Transport::new(
+            start_frame,
+            duration_to_frames(self.seek_fade, config.sample_rate),
+        ));
+        let frame_size = config.channels as usize;
+        let duration = decoder.duration() as u64;
+        let session = self.session + 1;
+        // The audio callback doesn't wake the workers, so while it plays
+        // they poll as often as it plays half a ring buffer
+        let poll_interval = Duration::from_secs_f64(
+            (BUFFER_SIZE * 3 / 2 / frame_size) as f64
+                / config.sample_rate as f64,
+        );
+
+        // Dropping the handle joins the thread again if building the stream
+        // fails
+        let error_sender = self.error_sender.clone();
+        let decoder_thread = decoder_thread::spawn(
+            Source::new(decoder, mixer, resampler, frame_size),
+            input_producer,
+            segment_producer,
+            transport.clone(),
+            self.event_sender.clone(),
+            poll_interval,
+            move |err| {
+                let _ = error_sender.send((session, err));
+            },
+        );
+        let (spectrum_input, spectrum_output) =
+            triple_buffer(&FftSpectrum::empty());
+        let (waveform_input, waveform_output) =
+            triple_buffer(&Waveform::empty());
+        let (levels_input, levels_output) = triple_buffer(&Levels::empty());
+        let (analysis_sender, analysis_receiver) = mpsc::channel();
+        let analysis_thread = analysis::spawn(
+            tap_consumer,
+            analysis::TapFormat {
+                sample_rate: config.sample_rate,
+                channels: frame_size,
+                clip_level,
+            },
+            self.analysis_config,
+            analysis_receiver,
+            analysis::Outputs {
+                spectrum: spectrum_input,
+                waveform: waveform_input,
+                levels: levels_input,
+            },
+            poll_interval,
+        );
+        let mut output = Output::new(
+            frame_size,
+            config.sample_rate,
+            Input {
+                samples: input_consumer,
+                segments: segment_consumer,
+            },
+            Tap {
+                samples: tap_producer,
+            },
+            transport.clone(),
+            self.gains.clone(),
+        );
+
+        let error_sender = self.error_sender.clone();
+        let event_sender = self.event_sender.clone();
+        let stream = self.backend.build_stream(
+            config,
+            Box::new(move |data, info| output.process(data, info)),
+            Box::new(move |err| {
+                let _ = event_sender
+                    .unbounded_send(PlayerEvent::StreamError(err.clone()));
+                let _ = error_sender.send((session, err));
+            }),
+        )?;
+
+        self.decoder_thread = Some(decoder_thread);
+        self.analysis_thread = Some(analysis_thread);
+        self.spectrum = Some(spectrum_output);
+        self.waveform = Some(waveform_output);
+        self.levels = Some(levels_output);
+        self.analysis_sender = Some(analysis_sender);
+        self.recovery = None;
+        self.session = session;
 
         self.sample_rate = cpal::SampleRate(spec.sample_rate);
         self.channels = spec.channels;
-        self.position.store(0, Ordering::Relaxed);
-        self.duration = reader.duration();
-        let position = self.position.clone();
-        let channels = self.channels.clone();
+        self.output_sample_rate = cpal::SampleRate(config.sample_rate);
+        self.transport = transport;
+        self.duration = duration;
+        self.path = Some(path);
 
-        std::thread::spawn(move || loop {
-            let pos = position.load(Ordering::Relaxed);
+        if play {
+            stream.play()?;
+        }
+        self.stream = Some(stream);
+        self.is_playing = play;
+        self.set_workers_playing(play);
 
-            while input_producer.is_full() {
-                std::thread::sleep(Duration::from_millis(100));
-            }
+        Ok(spec)
+    }
 
-            let mut chunk = input_producer
-                .write_chunk(std::cmp::min(input_producer.slots(), BUFFER_SIZE))
-                .unwrap();
-
-            let mut samples = reader
-                .samples::<i16>()
-                .take(chunk.len())
-                .map(|s| s.expect("Failed to read sample"))
-                .collect::<Vec<i16>>();
-            samples.resize(chunk.len(), 0);
-                .map(|s| {
-                    cpal::Sample::from_sample(s.expect("Failed to read sample"))
-                })
-                .collect::<Vec<_>>();
-            samples.resize(chunk.len(), 0f32);
-
-            let (first, second) = chunk.as_mut_slices();
-            let mid = first.len();
-
-            first.copy_from_slice(&samples[..mid]);
-            second.copy_from_slice(&samples[mid..]);
-
-            chunk.commit_all();
-
-            match position.compare_exchange(
-                pos,
-                pos + (samples.len() as u16 / channels) as u32,
-                Ordering::Relaxed,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => (),
-                // Position was changed from set_position
-                Err(p) => reader.seek(p).expect("Failed to seek"),
-            };
-        });
-
-        let bin_size: f32 = self.sample_rate.0 as f32 / BUFFER_SIZE as f32 * 2.;
-        self.output_len = (MAX_FREQUENCY / bin_size).ceil() as usize;
-
-        let (mut output_producer, output_consumer) =
-            RingBuffer::new(BUFFER_SIZE * 3);
-        self.buffer_consumer = Some(output_consumer);
-
-        let supported_config = self.get_stream_config();
-
-        self.stream = Some(
-            self.device
-                .build_output_stream(
-                    &supported_config.into(),
-                    move |data, _| {
-                        process_samples(
-                            data,
-                            &mut input_consumer,
-                            &mut output_producer,
-                        )
-                    },
-                    move |_err| panic!("ERROR"),
-                    None,
-                )
-                .expect("Building output stream failed"),
-        );
-        self.is_playing = true;
+    /// Pauses and rewinds to the start of the loaded file
+    pub fn stop(&mut self) -> Result<(), PlayerError> {
+        self.pause()?;
+        self.set_position(0.);
+        Ok(())
+    }
+
+    /// Stops playback and closes the loaded file
+    pub fn unload(&mut self) {
+        self.stream = None;
+        self.decoder_thread = None;
+        self.analysis_thread = None;
+        self.spectrum = None;
+        self.waveform = None;
+        self.levels = None;
+        self.analysis_sender = None;
+        self.is_playing = false;
+        self.recovery = None;
+        self.path = None;
+        self.duration = 0;
+        self.transport = Arc::new(Transport::new(0, 0));
+        self.normalizer.load(None);
+    }
+
+    /// Sets the resampling quality used for files loaded from now on, when
+    /// the output can't run at the file's sample rate
+    pub fn set_resample_quality(&mut self, quality: ResampleQuality) {
+        self.resample_quality = quality;
+    }
+
+    /// Sets the length of the fade out and fade in around a seek, which keeps
+    /// the jump from clicking
+    pub fn set_seek_fade(&mut self, fade: Duration) {
+        self.seek_fade = fade;
+        self.transport.set_fade_frames(duration_to_frames(
+            fade,
+            self.output_sample_rate.0,
+        ));
     }
 
-    pub fn play(&mut self) {
+    pub fn play(&mut self) -> Result<(), PlayerError> {
+        if let Some(ref mut recovery) = self.recovery {
+            recovery.resume = true;
+            return Ok(());
+        }
         if let Some(ref stream) = self.stream {
-            stream.play().unwrap();
+            stream.play()?;
             self.is_playing = true;
+            self.set_workers_playing(true);
         }
+        Ok(())
     }
 
     pub fn set_position(&mut self, seconds: f32) {
-        let new_position = self.seconds_to_samples(seconds).max(0) as u32;
-        self.position.store(new_position, Ordering::Relaxed);
+        let frame = self.seconds_to_frames(seconds).min(self.duration);
+        self.transport.seek(frame);
+        if let Some(ref decoder_thread) = self.decoder_thread {
+            decoder_thread.wake();
+        }
+        let _ =
+            self.event_sender
+                .unbounded_send(PlayerEvent::PositionChanged {
+                    seconds: self.frames_to_seconds(frame),
+                });
     }
 
+    /// Position of the audio currently being heard, in seconds
     pub fn get_position(&self) -> f32 {
-        self.samples_to_seconds(self.position.load(Ordering::Relaxed))
+        self.frames_to_seconds(self.get_position_frames())
     }
 
     pub fn get_duration(&self) -> f32 {
-        self.samples_to_seconds(self.duration)
+        self.frames_to_seconds(self.duration)
+    }
+
+    /// Sample rate in Hz the output plays and the analysis measures at
+    pub fn get_output_sample_rate(&self) -> u32 {
+        self.output_sample_rate.0
+    }
+
+    /// Path of the loaded file
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
     }
 
     pub fn is_playing(&self) -> bool {
@@ -187,105 +583,256 @@ impl Player {
         self.stream.is_some()
     }
 
-    pub fn pause(&mut self) {
+    pub fn pause(&mut self) -> Result<(), PlayerError> {
+        if let Some(ref mut recovery) = self.recovery {
+            recovery.resume = false;
+            return Ok(());
+        }
         if let Some(ref stream) = self.stream {
-            stream.pause().unwrap();
+            stream.pause()?;
             self.is_playing = false;
+            self.set_workers_playing(false);
         }
+        Ok(())
     }
 
-    pub fn get_fft_spectrum(&mut self) -> &FftSpectrum {
-        if self.buffer_consumer == None {
-            return &self.fft_output;
+    /// Lets the decoder and analysis threads know whether the output plays,
+    /// so they only poll while it does
+    fn set_workers_playing(&self, playing: bool) {
+        for worker in [&self.decoder_thread, &self.analysis_thread]
+            .into_iter()
+            .flatten()
+        {
+            worker.set_playing(playing);
+        }
+    }
+
+    /// Number of times the output ran out of decoded audio since the file
+    /// was opened
+    pub fn get_underruns(&self) -> u64 {
+        self.transport.underruns()
+    }
+
+    /// Number of times the output produced audio faster than it was
+    /// analysed, dropping the samples that didn't fit
+    pub fn get_overruns(&self) -> u64 {
+        self.transport.overruns()
+    }
+
+    /// Whether playback is interrupted until an output device is available
+    pub fn is_recovering(&self) -> bool {
+        self.recovery.is_some()
+    }
+
+    /// Returns the next error reported by the decoder thread or the output
+    /// stream since the last call. Should be called regularly, since this is
+    /// also where a failed output stream is replaced.
+    pub fn poll_error(&mut self) -> Option<PlayerError> {
+        self.try_recover();
+
+        while let Ok((session, err)) = self.error_receiver.try_recv() {
+            if session != self.session {
+                continue;
+            }
+            if err.is_stream_error() && self.recovery.is_none() {
+                self.recovery = Some(Recovery {
+                    lost_device: self.backend.device_id(),
+                    resume: self.is_playing,
+                    last_attempt: None,
+                });
+                self.is_playing = false;
+                self.set_workers_playing(false);
+                self.try_recover();
+            }
+            return Some(err);
+        }
+
+        None
+    }
+
+    /// Rebuilds a failed stream at the last position, on the lost device if
+    /// it plays again or else on the current default device. Playback stays
+    /// paused until either works.
+    fn try_recover(&mut self) {
+        let Some(ref mut recovery) = self.recovery else {
+            return;
+        };
+        if recovery
+            .last_attempt
+            .is_some_and(|last| last.elapsed() < RECOVERY_INTERVAL)
+        {
+            return;
+        }
+        recovery.last_attempt = Some(Instant::now());
+
+        let Some(ref lost_device) = recovery.lost_device else {
+            // Not a hardware device, so there is nothing to wait for
+            if let Some(ref stream) = self.stream {
+                let _ = stream.pause();
+            }
+            self.recovery = None;
+            return;
+        };
+        let lost_device = lost_device.clone();
+        let resume = recovery.resume;
+        let position = self.get_position_frames();
+
+        // The lost device may still be listed but fail to play, like when it
+        // is busy or half removed, so the default device is tried whenever
+        // it fails
+        for fallback in [false, true] {
+            let backend = if fallback {
+                CpalBackend::default_output()
+            } else {
+                CpalBackend::open(&lost_device)
+            };
+            let Ok(backend) = backend else {
+                continue;
+            };
+
+            let previous =
+                std::mem::replace(&mut self.backend, Box::new(backend));
+            let Some(path) = self.path.clone() else {
+                self.recovery = None;
+                return;
+            };
+            if self.open(path, position, resume).is_ok() {
+                return;
+            }
+            self.backend = previous;
+        }
+    }
+
+    /// Changes how the spectrum is analysed, taking effect immediately for
+    /// the loaded file
+    pub fn set_analysis_config(&mut self, config: AnalysisConfig) {
+        self.analysis_config = config.validated();
+        if let Some(ref sender) = self.analysis_sender {
+            let _ =
+                sender.send(AnalysisCommand::Configure(self.analysis_config));
+        }
+        if let Some(ref analysis_thread) = self.analysis_thread {
+            analysis_thread.wake();
         }
+    }
 
-        let consumer = self.buffer_consumer.as_mut().unwrap();
-        if consumer.slots() < BUFFER_SIZE {
-            return &self.fft_output;
+    /// Starts measuring the integrated loudness and loudness range of what
+    /// is played again
+    pub fn reset_loudness(&mut self) {
+        if let Some(ref sender) = self.analysis_sender {
+            let _ = sender.send(AnalysisCommand::ResetLoudness);
+        }
+        if let Some(ref analysis_thread) = self.analysis_thread {
+            analysis_thread.wake();
         }
+    }
+
+    pub fn get_analysis_config(&self) -> AnalysisConfig {
+        self.analysis_config
+    }
 
-        let mut buffer: Vec<Complex<f32>> = vec![];
-        for i in 0..BUFFER_SIZE {
-            let sample = consumer.pop().unwrap();
-            buffer.push(Complex::new(
-                self.hamming_window[i] * sample.clone(),
-                0f32,
-            ));
+    /// Sets the volume in dB between `MIN_VOLUME`, which is silence, and
+    /// `MAX_VOLUME`. Changes are smoothed so they don't click.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(MIN_VOLUME, MAX_VOLUME);
+        self.update_volume();
+    }
+
+    pub fn get_volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Silences the output without losing the volume
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.update_volume();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn update_volume(&self) {
+        if self.muted || self.volume <= MIN_VOLUME {
+            self.gains.volume.set(0.);
+        } else {
+            self.gains.volume.set_db(self.volume);
         }
+    }
 
-        self.fft.process(&mut buffer);
+    /// Turns loudness normalization on or off or changes its target, taking
+    /// effect immediately for the loaded file
+    pub fn set_normalization(&mut self, config: NormalizationConfig) {
+        self.normalizer.set_config(config);
+    }
+
+    pub fn get_normalization(&self) -> NormalizationConfig {
+        self.normalizer.config()
+    }
+
+    /// Gain in dB the loaded file is normalized with, or `None` while it is
+    /// scanned or normalization is off
+    pub fn get_normalization_gain(&self) -> Option<f32> {
+        self.normalizer.gain()
+    }
+
+    /// Measures the loudness of the whole loaded file, reported through
+    /// `PlayerEvent::LoudnessScanned`. Files are scanned only once, also
+    /// when they were scanned for the normalization.
+    pub fn scan_loudness(&self) {
+        self.normalizer.scan();
+    }
+
+    /// Loudness of the whole loaded file, if it was scanned
+    pub fn get_loudness_report(&self) -> Option<LoudnessReport> {
+        self.normalizer.report()
+    }
 
-        self.fft_output.values = buffer
-            .iter()
-            .take(self.output_len)
-            .map(|elem| elem.norm())
-            .collect::<Vec<_>>();
+    /// Whether the loaded file is being scanned for its loudness
+    pub fn is_scanning_loudness(&self) -> bool {
+        self.normalizer.is_scanning()
+    }
 
-        self.fft_output.bin_size =
-            self.sample_rate.0 as f32 / BUFFER_SIZE as f32 * 2.;
+    /// Latest spectrum of the audio being played, analysed on a separate
+    /// thread
+    pub fn get_fft_spectrum(&mut self) -> &FftSpectrum {
+        match self.spectrum {
+            Some(ref mut spectrum) => spectrum.read(),
+            None => &self.empty_spectrum,
+        }
+    }
 
-        &self.fft_output
+    /// Latest frames of the audio being played, for drawing it over time
+    pub fn get_waveform(&mut self) -> &Waveform {
+        match self.waveform {
+            Some(ref mut waveform) => waveform.read(),
+            None => &self.empty_waveform,
+        }
     }
 
-    fn seconds_to_samples(&self, seconds: f32) -> i32 {
-        (self.sample_rate.0 as f32 * seconds) as i32
+    /// Latest level meter readings of the audio being played
+    pub fn get_levels(&mut self) -> &Levels {
+        match self.levels {
+            Some(ref mut levels) => levels.read(),
+            None => &self.empty_levels,
+        }
     }
 
-    fn samples_to_seconds(&self, samples: u32) -> f32 {
-        samples as f32 / self.sample_rate.0 as f32
+    fn get_position_frames(&self) -> u64 {
+        let ratio =
+            self.sample_rate.0 as f64 / self.output_sample_rate.0 as f64;
+        self.transport.position(ratio).min(self.duration)
     }
 
-    fn get_stream_config(&self) -> SupportedStreamConfig {
-        let mut supported_configs_range = self
-            .device
-            .supported_output_configs()
-            .expect("error while querying configs");
+    fn seconds_to_frames(&self, seconds: f32) -> u64 {
+        (self.sample_rate.0 as f64 * seconds.max(0.) as f64) as u64
+    }
 
-        supported_configs_range
-            .find(|range| {
-                range.sample_format() == cpal::SampleFormat::F32
-                    && range.max_sample_rate() >= self.sample_rate
-                    && range.min_sample_rate() <= self.sample_rate
-                    && range.channels() == self.channels
-            })
-            .expect("Could not find supported audio config")
-            .with_sample_rate(self.sample_rate)
+    fn frames_to_seconds(&self, frames: u64) -> f32 {
+        (frames as f64 / self.sample_rate.0 as f64) as f32
     }
 }
 
-fn process_samples(
-    samples: &mut [f32],
-    input_consumer: &mut Consumer<f32>,
-    output_producer: &mut Producer<f32>,
-) {
-    let read_chunk = input_consumer
-        .read_chunk(std::cmp::min(samples.len(), input_consumer.slots()))
-        .unwrap();
-
-    let (first, second) = read_chunk.as_slices();
-    let mut input_samples = [first, second]
-        .concat()
-        .iter()
-        .map(|sample| *sample)
-        .collect::<Vec<_>>();
-    input_samples.resize(samples.len(), 0f32);
-    samples.copy_from_slice(input_samples.as_slice());
-    read_chunk.commit_all();
-
-    let write_slots = std::cmp::min(samples.len(), output_producer.slots());
-    if write_slots != samples.len() {
-        // How to fix?
-        // - Increase BUFFER_SIZE
-        // - Optimize UI thread
-        // - Decrease spectrum resolution
-        eprintln!("Output is being processed too slow")
-    }
-
-    let mut write_chunk = output_producer.write_chunk(write_slots).unwrap();
-    let (first, second) = write_chunk.as_mut_slices();
-    let mid = first.len();
-
-    first.copy_from_slice(&samples[..mid]);
-    second.copy_from_slice(&samples[mid..]);
-    write_chunk.commit_all();
+fn duration_to_frames(duration: Duration, sample_rate: u32) -> u64 {
+    (duration.as_secs_f64() * sample_rate as f64).round() as u64
 }
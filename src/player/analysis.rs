@@ -0,0 +1,313 @@
+mod meter;
+mod window;
+
+use super::loudness::LoudnessMeter;
+use super::worker::Worker;
+use super::{FftSpectrum, Levels, Waveform};
+use meter::Meter;
+use rtrb::Consumer;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
+use triple_buffer::Input;
+pub use window::WindowFunction;
+
+/// Lowest level reported, instead of minus infinity for silence
+pub const MIN_DBFS: f32 = -200.;
+
+pub const MIN_FFT_SIZE: usize = 512;
+pub const MAX_FFT_SIZE: usize = 32768;
+
+/// Length of the waveform published for the oscilloscope, in seconds
+const WAVEFORM_DURATION: f32 = 0.5;
+
+/// Largest overlap between consecutive blocks, which keeps at least some new
+/// samples in every block
+pub const MAX_OVERLAP: f32 = 0.95;
+
+pub const MIN_RMS_TIME: Duration = Duration::from_millis(10);
+pub const MAX_RMS_TIME: Duration = Duration::from_secs(3);
+
+/// How the analysis thread turns the played audio into a spectrum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisConfig {
+    /// Number of frames per FFT, a power of two between `MIN_FFT_SIZE` and
+    /// `MAX_FFT_SIZE`
+    pub fft_size: usize,
+    pub window: WindowFunction,
+    /// Fraction of each block shared with the previous one
+    pub overlap: f32,
+    /// Integration time of the RMS level meters
+    pub rms_time: Duration,
+}
+
+impl AnalysisConfig {
+    pub const FFT_SIZES: [usize; 7] =
+        [512, 1024, 2048, 4096, 8192, 16384, 32768];
+
+    /// Number of new frames between consecutive FFTs
+    pub fn hop_size(&self) -> usize {
+        ((self.fft_size as f32 * (1. - self.overlap)).round() as usize).max(1)
+    }
+
+    /// Returns the config with the FFT size rounded to a supported power of
+    /// two, the overlap limited to `0..=MAX_OVERLAP` and the RMS time to
+    /// `MIN_RMS_TIME..=MAX_RMS_TIME`
+    pub fn validated(self) -> Self {
+        Self {
+            fft_size: self
+                .fft_size
+                .clamp(MIN_FFT_SIZE, MAX_FFT_SIZE)
+                .next_power_of_two(),
+            overlap: self.overlap.clamp(0., MAX_OVERLAP),
+            rms_time: self.rms_time.clamp(MIN_RMS_TIME, MAX_RMS_TIME),
+            ..self
+        }
+    }
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            fft_size: 4096,
+            window: WindowFunction::default(),
+            overlap: 0.,
+            rms_time: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Requests to the analysis thread
+pub enum AnalysisCommand {
+    Configure(AnalysisConfig),
+    /// Start measuring the integrated loudness and loudness range again
+    ResetLoudness,
+}
+
+/// Buffers sized for one analysis config
+pub struct Analyzer {
+    config: AnalysisConfig,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// The last `fft_size` frames read from the tap, per channel, oldest
+    /// first
+    history: Vec<Vec<f32>>,
+    /// Complex spectrum of each channel
+    buffers: Vec<Vec<Complex<f32>>>,
+    scratch: Vec<Complex<f32>>,
+    /// Turns a bin magnitude into the amplitude of a sine in that bin, so a
+    /// full scale sine reads 0 dBFS whatever the window and FFT size
+    gain: f32,
+}
+
+impl Analyzer {
+    pub fn new(config: AnalysisConfig, channels: usize) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(config.fft_size);
+
+        let window = config.window.coefficients(config.fft_size);
+
+        Self {
+            // A real sine splits its energy over the positive and negative
+            // frequencies, and the window scales it by its coherent gain
+            gain: 2. / window.iter().sum::<f32>(),
+            window,
+            history: vec![vec![0f32; config.fft_size]; channels],
+            buffers: vec![
+                vec![Complex::new(0f32, 0f32); config.fft_size];
+                channels
+            ],
+            scratch: vec![
+                Complex::new(0f32, 0f32);
+                fft.get_inplace_scratch_len()
+            ],
+            config,
+            fft,
+        }
+    }
+
+    /// Analyzer for another config that starts out with the most recent
+    /// frames of this one, so a larger FFT isn't fed silence
+    fn reconfigure(self, config: AnalysisConfig) -> Self {
+        let mut analyzer = Self::new(config, self.history.len());
+        for (history, previous) in analyzer.history.iter_mut().zip(self.history)
+        {
+            let len = history.len().min(previous.len());
+            let start = history.len() - len;
+            history[start..].copy_from_slice(&previous[previous.len() - len..]);
+        }
+        analyzer
+    }
+
+    /// Takes the most recent interleaved frames
+    pub fn push(&mut self, first: &[f32], second: &[f32]) {
+        append(&mut self.history, first, second);
+    }
+
+    /// Writes the spectra of the most recent `fft_size` frames to `spectrum`
+    pub fn process(&mut self, sample_rate: u32, spectrum: &mut FftSpectrum) {
+        for (buffer, history) in self.buffers.iter_mut().zip(&self.history) {
+            for ((value, sample), gain) in
+                buffer.iter_mut().zip(history).zip(&self.window)
+            {
+                *value = Complex::new(gain * sample, 0f32);
+            }
+            self.fft.process_with_scratch(buffer, &mut self.scratch);
+        }
+
+        // Bins up to and including Nyquist
+        let output_len = self.config.fft_size / 2 + 1;
+        let gain = self.gain;
+        let to_dbfs = |value: Complex<f32>| {
+            (20. * (value.norm() * gain).log10()).max(MIN_DBFS)
+        };
+
+        spectrum.channels.resize(self.buffers.len(), vec![]);
+        for (values, buffer) in spectrum.channels.iter_mut().zip(&self.buffers)
+        {
+            values.clear();
+            values.extend(buffer[..output_len].iter().copied().map(to_dbfs));
+        }
+
+        // The FFT is linear, so mid and side follow from the channel spectra
+        let left = &self.buffers[0][..output_len];
+        let right = &self.buffers[self.buffers.len().min(2) - 1][..output_len];
+        spectrum.mid.clear();
+        spectrum.mid.extend(
+            left.iter()
+                .zip(right)
+                .map(|(left, right)| to_dbfs((left + right) * 0.5)),
+        );
+        spectrum.side.clear();
+        spectrum.side.extend(
+            left.iter()
+                .zip(right)
+                .map(|(left, right)| to_dbfs((left - right) * 0.5)),
+        );
+
+        spectrum.bin_size = sample_rate as f32 / self.config.fft_size as f32;
+    }
+}
+
+/// Appends interleaved frames to histories of equal length per channel,
+/// keeping only the most recent frames. The frames come as a chunk of the
+/// tap, which is split into `first` and `second` where the ring buffer wraps
+/// around, and the meters and the analyzer take them the same way.
+fn append(histories: &mut [Vec<f32>], first: &[f32], second: &[f32]) {
+    let channels = histories.len();
+    let len = histories[0].len();
+    let frames = (first.len() + second.len()) / channels;
+    let kept = frames.min(len);
+    let skipped = frames - kept;
+    let start = len - kept;
+
+    for history in histories.iter_mut() {
+        history.copy_within(kept.., 0);
+    }
+    for (i, sample) in first
+        .iter()
+        .chain(second)
+        .enumerate()
+        .skip(skipped * channels)
+    {
+        histories[i % channels][start + i / channels - skipped] = *sample;
+    }
+}
+
+/// Where the analysis thread publishes its results
+pub struct Outputs {
+    pub spectrum: Input<FftSpectrum>,
+    pub waveform: Input<Waveform>,
+    pub levels: Input<Levels>,
+}
+
+/// Audio the output writes to the tap
+pub struct TapFormat {
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Level from which a sample counts as clipped, the largest positive
+    /// sample of the file
+    pub clip_level: f32,
+}
+
+/// Spawns the thread that analyses what the output plays. It looks for new
+/// frames in the tap every `poll_interval` while the output plays, runs a windowed FFT of every
+/// channel every hop over the most recent frames in the tap and publishes the
+/// spectra, so readers only ever see finished results. The levels and the
+/// most recent frames are published whenever new ones come in. Commands are
+/// picked up from `commands` without interrupting playback.
+pub fn spawn(
+    mut tap: Consumer<f32>,
+    format: TapFormat,
+    config: AnalysisConfig,
+    commands: Receiver<AnalysisCommand>,
+    mut outputs: Outputs,
+    poll_interval: Duration,
+) -> Worker {
+    Worker::spawn(move |signals| {
+        let TapFormat {
+            sample_rate,
+            channels,
+            clip_level,
+        } = format;
+        let mut analyzer = Analyzer::new(config, channels);
+        let mut meter =
+            Meter::new(sample_rate, channels, config.rms_time, clip_level);
+        let mut loudness = LoudnessMeter::new(sample_rate, channels);
+        let waveform_len = (sample_rate as f32 * WAVEFORM_DURATION) as usize;
+        let mut recent = vec![vec![0f32; waveform_len]; channels];
+        // Frames read since the last FFT
+        let mut pending = 0;
+
+        while !signals.is_shutdown() {
+            let mut config = None;
+            for command in commands.try_iter() {
+                match command {
+                    AnalysisCommand::Configure(new) => config = Some(new),
+                    AnalysisCommand::ResetLoudness => loudness.reset(),
+                }
+            }
+            if let Some(config) = config {
+                if config.rms_time != analyzer.config.rms_time {
+                    meter.set_rms_time(sample_rate, config.rms_time);
+                }
+                analyzer = analyzer.reconfigure(config);
+                pending = 0;
+            }
+
+            // The output only writes whole frames
+            let frames = tap.slots() / channels;
+            if frames == 0 {
+                signals.park(poll_interval);
+                continue;
+            }
+
+            // Every frame is metered, so no peak goes unnoticed
+            let chunk = tap.read_chunk(frames * channels).unwrap();
+            let (first, second) = chunk.as_slices();
+            meter.push(first, second);
+            loudness.push(first, second);
+            analyzer.push(first, second);
+            append(&mut recent, first, second);
+            chunk.commit_all();
+
+            let levels = outputs.levels.input_buffer();
+            meter.read(levels);
+            levels.loudness = loudness.loudness();
+            outputs.levels.publish();
+
+            let waveform = outputs.waveform.input_buffer();
+            waveform.sample_rate = sample_rate;
+            waveform.channels.clone_from(&recent);
+            outputs.waveform.publish();
+
+            pending += frames;
+            if pending >= analyzer.config.hop_size() {
+                pending = 0;
+                analyzer.process(sample_rate, outputs.spectrum.input_buffer());
+                outputs.spectrum.publish();
+            }
+        }
+    })
+}
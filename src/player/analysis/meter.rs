@@ -0,0 +1,127 @@
+use super::MIN_DBFS;
+use crate::player::true_peak::TruePeak;
+use crate::player::{ChannelLevels, Levels};
+use std::time::Duration;
+
+/// Rate in dB per second at which the peak readings fall back
+const PEAK_FALL: f32 = 20.;
+
+/// How long the highest peak is held
+const PEAK_HOLD: Duration = Duration::from_secs(2);
+
+/// Level meters of every channel, fed with the frames that are played
+pub struct Meter {
+    channels: Vec<ChannelMeter>,
+    /// Per sample factor of the peak fall back
+    fall: f32,
+    hold_frames: usize,
+    /// Level from which a sample counts as clipped
+    clip_level: f32,
+}
+
+struct ChannelMeter {
+    /// Squared samples of the RMS window
+    squares: Vec<f32>,
+    index: usize,
+    sum: f64,
+    true_peak_filter: TruePeak,
+    /// Linear peak readings, which fall back after a peak
+    peak: f32,
+    true_peak: f32,
+    hold: f32,
+    hold_age: usize,
+    clips: u64,
+}
+
+impl Meter {
+    /// Meters `channels` channels with RMS measured over `rms_time`, counting
+    /// samples at `clip_level` or above as clipped
+    pub fn new(
+        sample_rate: u32,
+        channels: usize,
+        rms_time: Duration,
+        clip_level: f32,
+    ) -> Self {
+        let rms_frames =
+            ((rms_time.as_secs_f32() * sample_rate as f32) as usize).max(1);
+        let channel = || ChannelMeter {
+            squares: vec![0f32; rms_frames],
+            index: 0,
+            sum: 0.,
+            true_peak_filter: TruePeak::new(),
+            peak: 0.,
+            true_peak: 0.,
+            hold: 0.,
+            hold_age: 0,
+            clips: 0,
+        };
+
+        Self {
+            channels: (0..channels).map(|_| channel()).collect(),
+            fall: 10f32.powf(-PEAK_FALL / 20. / sample_rate as f32),
+            hold_frames: (PEAK_HOLD.as_secs_f32() * sample_rate as f32)
+                as usize,
+            clip_level,
+        }
+    }
+
+    /// Changes the RMS window, which starts out empty again
+    pub fn set_rms_time(&mut self, sample_rate: u32, rms_time: Duration) {
+        let rms_frames =
+            ((rms_time.as_secs_f32() * sample_rate as f32) as usize).max(1);
+        for channel in &mut self.channels {
+            channel.squares = vec![0f32; rms_frames];
+            channel.index = 0;
+            channel.sum = 0.;
+        }
+    }
+
+    /// Updates the readings and clip counts with the interleaved frames in
+    /// `first` and then `second`
+    pub fn push(&mut self, first: &[f32], second: &[f32]) {
+        let channels = self.channels.len();
+        for (i, sample) in first.iter().chain(second).enumerate() {
+            let channel = &mut self.channels[i % channels];
+            let sample = *sample;
+
+            let square = sample * sample;
+            channel.sum +=
+                square as f64 - channel.squares[channel.index] as f64;
+            channel.squares[channel.index] = square;
+            channel.index = (channel.index + 1) % channel.squares.len();
+
+            let true_peak = channel.true_peak_filter.push(sample);
+
+            if sample.abs() >= self.clip_level {
+                channel.clips += 1;
+            }
+            channel.peak = (channel.peak * self.fall).max(sample.abs());
+            channel.true_peak = (channel.true_peak * self.fall).max(true_peak);
+
+            channel.hold_age += 1;
+            if true_peak >= channel.hold || channel.hold_age > self.hold_frames
+            {
+                channel.hold = true_peak;
+                channel.hold_age = 0;
+            }
+        }
+    }
+
+    /// Writes the current readings into `levels`
+    pub fn read(&self, levels: &mut Levels) {
+        let to_dbfs = |value: f32| (20. * value.log10()).max(MIN_DBFS);
+        levels.channels.clear();
+        levels.channels.extend(self.channels.iter().map(|channel| {
+            ChannelLevels {
+                peak: to_dbfs(channel.peak),
+                rms: to_dbfs(
+                    (channel.sum.max(0.) / channel.squares.len() as f64).sqrt()
+                        as f32,
+                ),
+                true_peak: to_dbfs(channel.true_peak),
+                peak_hold: to_dbfs(channel.hold),
+                clips: channel.clips,
+            }
+        }));
+    }
+}
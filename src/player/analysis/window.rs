@@ -0,0 +1,102 @@
+use apodize::{blackman_iter, hamming_iter, hanning_iter};
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Shape parameter of the Kaiser window, trading main lobe width for side
+/// lobes around -90 dB
+const KAISER_BETA: f64 = 8.6;
+
+/// Coefficients of the 5-term flat-top window, as used by SciPy and MATLAB
+const FLAT_TOP: [f64; 5] = [
+    0.21557895,
+    0.41663158,
+    0.277263158,
+    0.083578947,
+    0.006947368,
+];
+
+/// Window applied to each block before the FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowFunction {
+    Hann,
+    #[default]
+    Hamming,
+    /// 4-term Blackman-Harris, low leakage at the cost of a wider main lobe
+    BlackmanHarris,
+    /// Accurate amplitudes for tones between bins, poor frequency resolution
+    FlatTop,
+    Kaiser,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 5] = [
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::FlatTop,
+        WindowFunction::Kaiser,
+    ];
+
+    /// Window coefficients for a block of `size` samples
+    pub fn coefficients(&self, size: usize) -> Vec<f32> {
+        let to_f32 = |value: f64| value as f32;
+        match self {
+            WindowFunction::Hann => hanning_iter(size).map(to_f32).collect(),
+            WindowFunction::Hamming => hamming_iter(size).map(to_f32).collect(),
+            // apodize's Blackman window uses the Blackman-Harris coefficients
+            WindowFunction::BlackmanHarris => {
+                blackman_iter(size).map(to_f32).collect()
+            }
+            WindowFunction::FlatTop => (0..size)
+                .map(|index| {
+                    let x = 2. * PI * index as f64 / (size - 1) as f64;
+                    FLAT_TOP
+                        .iter()
+                        .enumerate()
+                        .map(|(k, a)| {
+                            let sign = if k % 2 == 0 { 1. } else { -1. };
+                            sign * a * (k as f64 * x).cos()
+                        })
+                        .sum::<f64>() as f32
+                })
+                .collect(),
+            WindowFunction::Kaiser => {
+                let denominator = bessel_i0(KAISER_BETA);
+                (0..size)
+                    .map(|index| {
+                        let x = 2. * index as f64 / (size - 1) as f64 - 1.;
+                        let value =
+                            bessel_i0(KAISER_BETA * (1. - x * x).sqrt());
+                        (value / denominator) as f32
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowFunction::Hann => write!(f, "Hann"),
+            WindowFunction::Hamming => write!(f, "Hamming"),
+            WindowFunction::BlackmanHarris => write!(f, "Blackman-Harris"),
+            WindowFunction::FlatTop => write!(f, "Flat-top"),
+            WindowFunction::Kaiser => write!(f, "Kaiser"),
+        }
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind, from its power
+/// series
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.;
+    let mut term = 1.;
+    let mut k = 1.;
+    while term > sum * 1e-12 {
+        term *= (x / (2. * k)).powi(2);
+        sum += term;
+        k += 1.;
+    }
+    sum
+}
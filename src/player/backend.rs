@@ -0,0 +1,152 @@
+mod cpal_backend;
+mod null;
+mod wav;
+
+pub use cpal_backend::{
+    ConfigRange, CpalBackend, DeviceId, DeviceInfo, HostInfo,
+};
+pub use null::NullBackend;
+pub use wav::WavBackend;
+
+use super::PlayerError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Number of frames rendered per callback by the clocked backends
+const CLOCK_PERIOD_FRAMES: usize = 1024;
+
+pub type DataCallback =
+    Box<dyn FnMut(&mut [f32], &CallbackInfo) + Send + 'static>;
+pub type ErrorCallback = Box<dyn FnMut(PlayerError) + Send + 'static>;
+
+/// Timing information passed to every data callback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallbackInfo {
+    /// Time between the callback and the buffer it fills being played
+    pub latency: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// A destination for the interleaved f32 samples produced by `Player`
+pub trait OutputBackend {
+    /// The device this backend plays through, if it is a real device
+    fn device_id(&self) -> Option<DeviceId> {
+        None
+    }
+
+    /// Picks the configuration a stream for audio in the `requested`
+    /// configuration should run at. `Player` resamples when the sample rates
+    /// differ.
+    fn stream_config(
+        &self,
+        requested: StreamConfig,
+    ) -> Result<StreamConfig, PlayerError> {
+        Ok(requested)
+    }
+
+    fn build_stream(
+        &self,
+        config: StreamConfig,
+        callback: DataCallback,
+        error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, PlayerError>;
+}
+
+/// A running stream created by an `OutputBackend`
+pub trait OutputStream {
+    fn play(&self) -> Result<(), PlayerError>;
+    fn pause(&self) -> Result<(), PlayerError>;
+}
+
+/// Pulls buffers from a data callback on a dedicated thread at the pace a
+/// real device would, handing every rendered buffer to `sink`
+struct ClockedStream {
+    playing: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ClockedStream {
+    fn spawn<S>(
+        config: StreamConfig,
+        mut callback: DataCallback,
+        mut sink: S,
+    ) -> Self
+    where
+        S: FnMut(&[f32]) + Send + 'static,
+    {
+        let playing = Arc::new(AtomicBool::new(false));
+        let running = Arc::new(AtomicBool::new(true));
+        let period = Duration::from_secs_f64(
+            CLOCK_PERIOD_FRAMES as f64 / config.sample_rate.max(1) as f64,
+        );
+
+        let thread = {
+            let playing = playing.clone();
+            let running = running.clone();
+            std::thread::spawn(move || {
+                let mut buffer =
+                    vec![0f32; CLOCK_PERIOD_FRAMES * config.channels as usize];
+                let mut deadline = Instant::now();
+
+                while running.load(Ordering::Relaxed) {
+                    if !playing.load(Ordering::Relaxed) {
+                        std::thread::park_timeout(period);
+                        deadline = Instant::now();
+                        continue;
+                    }
+
+                    callback(&mut buffer, &CallbackInfo::default());
+                    sink(&buffer);
+
+                    deadline += period;
+                    let now = Instant::now();
+                    if deadline > now {
+                        std::thread::sleep(deadline - now);
+                    } else {
+                        // Fell behind, don't try to catch up in a burst
+                        deadline = now;
+                    }
+                }
+            })
+        };
+
+        Self {
+            playing,
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl OutputStream for ClockedStream {
+    fn play(&self) -> Result<(), PlayerError> {
+        self.playing.store(true, Ordering::Relaxed);
+        if let Some(ref thread) = self.thread {
+            thread.thread().unpark();
+        }
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), PlayerError> {
+        self.playing.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for ClockedStream {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use super::{
+    CallbackInfo, DataCallback, ErrorCallback, OutputBackend, OutputStream,
+    StreamConfig,
+};
+use crate::player::PlayerError;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    Device, HostId, SampleFormat, Stream, StreamError, SupportedStreamConfig,
+    SupportedStreamConfigRange,
+};
+use std::fmt;
+
+/// Identifies an output device across the available hosts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceId {
+    pub host: HostId,
+    pub name: String,
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.host.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: SampleFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    pub id: DeviceId,
+    pub is_default: bool,
+    pub configs: Vec<ConfigRange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    pub id: HostId,
+    pub devices: Vec<DeviceInfo>,
+}
+
+/// Plays audio through a cpal output device
+pub struct CpalBackend {
+    id: DeviceId,
+    device: Device,
+}
+
+impl CpalBackend {
+    pub fn new(host: HostId, device: Device) -> Self {
+        let id = DeviceId {
+            host,
+            name: device.name().unwrap_or_default(),
+        };
+        Self { id, device }
+    }
+
+    /// Uses the default output device of the default host
+    pub fn default_output() -> Result<Self, PlayerError> {
+        let host = cpal::default_host();
+        host.default_output_device()
+            .map(|device| Self::new(host.id(), device))
+            .ok_or(PlayerError::NoOutputDevice)
+    }
+
+    pub fn open(id: &DeviceId) -> Result<Self, PlayerError> {
+        let not_found = || PlayerError::DeviceNotFound(id.to_string());
+
+        cpal::host_from_id(id.host)
+            .map_err(|_| not_found())?
+            .output_devices()
+            .map_err(|_| not_found())?
+            .find(|device| device.name().is_ok_and(|name| name == id.name))
+            .map(|device| Self::new(id.host, device))
+            .ok_or_else(not_found)
+    }
+
+    /// Lists the output devices of every available host together with the
+    /// configurations they support
+    pub fn list_hosts() -> Vec<HostInfo> {
+        cpal::available_hosts()
+            .into_iter()
+            .filter_map(|id| cpal::host_from_id(id).ok())
+            .map(|host| {
+                let default_name = host
+                    .default_output_device()
+                    .and_then(|device| device.name().ok());
+                let devices = match host.output_devices() {
+                    Ok(devices) => devices
+                        .filter_map(|device| {
+                            get_device_info(host.id(), &device, &default_name)
+                        })
+                        .collect(),
+                    Err(_) => vec![],
+                };
+
+                HostInfo {
+                    id: host.id(),
+                    devices,
+                }
+            })
+            .collect()
+    }
+
+    /// Supported f32 configurations of the device
+    fn get_config_ranges(
+        &self,
+    ) -> Result<Vec<SupportedStreamConfigRange>, PlayerError> {
+        Ok(self
+            .device
+            .supported_output_configs()
+            .map_err(|err| PlayerError::BuildStream(err.to_string()))?
+            .filter(|range| range.sample_format() == cpal::SampleFormat::F32)
+            .collect())
+    }
+
+    fn get_stream_config(
+        &self,
+        config: StreamConfig,
+    ) -> Result<SupportedStreamConfig, PlayerError> {
+        let sample_rate = cpal::SampleRate(config.sample_rate);
+
+        self.get_config_ranges()?
+            .into_iter()
+            .find(|range| {
+                range.channels() == config.channels
+                    && supports_rate(range, sample_rate)
+            })
+            .map(|range| range.with_sample_rate(sample_rate))
+            .ok_or(PlayerError::UnsupportedConfig {
+                sample_rate: config.sample_rate,
+                channels: config.channels,
+            })
+    }
+}
+
+impl OutputBackend for CpalBackend {
+    fn device_id(&self) -> Option<DeviceId> {
+        Some(self.id.clone())
+    }
+
+    /// Runs at the requested sample rate when possible, so files play
+    /// without resampling, or else at the device's default rate. Falls back
+    /// to stereo or any other channel count the device offers when it can't
+    /// play the requested channels.
+    fn stream_config(
+        &self,
+        requested: StreamConfig,
+    ) -> Result<StreamConfig, PlayerError> {
+        let all_ranges = self.get_config_ranges()?;
+        let unsupported = PlayerError::UnsupportedConfig {
+            sample_rate: requested.sample_rate,
+            channels: requested.channels,
+        };
+
+        let channels = [requested.channels, 2]
+            .into_iter()
+            .chain(all_ranges.iter().map(|range| range.channels()))
+            .find(|&channels| {
+                all_ranges.iter().any(|range| range.channels() == channels)
+            })
+            .ok_or(unsupported.clone())?;
+        let ranges = all_ranges
+            .into_iter()
+            .filter(|range| range.channels() == channels)
+            .collect::<Vec<_>>();
+
+        let default_rate = self
+            .device
+            .default_output_config()
+            .map(|config| config.sample_rate())
+            .ok();
+        let sample_rate =
+            [Some(cpal::SampleRate(requested.sample_rate)), default_rate]
+                .into_iter()
+                .flatten()
+                .find(|&rate| {
+                    ranges.iter().any(|range| supports_rate(range, rate))
+                })
+                .or_else(|| ranges.first().map(|range| range.max_sample_rate()))
+                .ok_or(unsupported)?;
+
+        Ok(StreamConfig {
+            sample_rate: sample_rate.0,
+            channels,
+        })
+    }
+
+    fn build_stream(
+        &self,
+        config: StreamConfig,
+        mut callback: DataCallback,
+        mut error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, PlayerError> {
+        let supported_config = self.get_stream_config(config)?;
+
+        let stream = self
+            .device
+            .build_output_stream(
+                &supported_config.into(),
+                move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                    let timestamp = info.timestamp();
+                    let latency = timestamp
+                        .playback
+                        .duration_since(&timestamp.callback)
+                        .unwrap_or_default();
+                    callback(data, &CallbackInfo { latency })
+                },
+                move |err| {
+                    error_callback(match err {
+                        StreamError::DeviceNotAvailable => {
+                            PlayerError::DeviceDisconnected
+                        }
+                        err => PlayerError::Stream(err.to_string()),
+                    })
+                },
+                None,
+            )
+            .map_err(|err| PlayerError::BuildStream(err.to_string()))?;
+
+        Ok(Box::new(CpalStream { stream }))
+    }
+}
+
+fn get_device_info(
+    host: HostId,
+    device: &Device,
+    default_name: &Option<String>,
+) -> Option<DeviceInfo> {
+    let name = device.name().ok()?;
+    let configs = match device.supported_output_configs() {
+        Ok(ranges) => ranges
+            .map(|range| ConfigRange {
+                channels: range.channels(),
+                min_sample_rate: range.min_sample_rate().0,
+                max_sample_rate: range.max_sample_rate().0,
+                sample_format: range.sample_format(),
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    Some(DeviceInfo {
+        is_default: default_name.as_ref() == Some(&name),
+        id: DeviceId { host, name },
+        configs,
+    })
+}
+
+fn supports_rate(
+    range: &SupportedStreamConfigRange,
+    sample_rate: cpal::SampleRate,
+) -> bool {
+    range.min_sample_rate() <= sample_rate
+        && range.max_sample_rate() >= sample_rate
+}
+
+struct CpalStream {
+    stream: Stream,
+}
+
+impl OutputStream for CpalStream {
+    fn play(&self) -> Result<(), PlayerError> {
+        self.stream
+            .play()
+            .map_err(|err| PlayerError::PlayStream(err.to_string()))
+    }
+
+    fn pause(&self) -> Result<(), PlayerError> {
+        self.stream
+            .pause()
+            .map_err(|err| PlayerError::PauseStream(err.to_string()))
+    }
+}
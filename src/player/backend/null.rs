@@ -0,0 +1,21 @@
+use super::{
+    ClockedStream, DataCallback, ErrorCallback, OutputBackend, OutputStream,
+    StreamConfig,
+};
+use crate::player::PlayerError;
+
+/// Consumes samples in real time and discards them, for machines without a
+/// sound card
+#[derive(Default)]
+pub struct NullBackend;
+
+impl OutputBackend for NullBackend {
+    fn build_stream(
+        &self,
+        config: StreamConfig,
+        callback: DataCallback,
+        _error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, PlayerError> {
+        Ok(Box::new(ClockedStream::spawn(config, callback, |_| {})))
+    }
+}
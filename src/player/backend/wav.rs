@@ -0,0 +1,55 @@
+use super::{
+    ClockedStream, DataCallback, ErrorCallback, OutputBackend, OutputStream,
+    StreamConfig,
+};
+use crate::player::PlayerError;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::path::PathBuf;
+
+/// Consumes samples in real time and records them to a 32-bit float WAV file.
+/// The file is recreated every time a stream is built.
+pub struct WavBackend {
+    path: PathBuf,
+}
+
+impl WavBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutputBackend for WavBackend {
+    fn build_stream(
+        &self,
+        config: StreamConfig,
+        callback: DataCallback,
+        mut error_callback: ErrorCallback,
+    ) -> Result<Box<dyn OutputStream>, PlayerError> {
+        let spec = WavSpec {
+            channels: config.channels,
+            sample_rate: config.sample_rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(&self.path, spec)
+            .map_err(|err| PlayerError::BuildStream(err.to_string()))?;
+        let mut failed = false;
+
+        Ok(Box::new(ClockedStream::spawn(
+            config,
+            callback,
+            move |samples| {
+                if failed {
+                    return;
+                }
+                for &sample in samples {
+                    if let Err(err) = writer.write_sample(sample) {
+                        failed = true;
+                        error_callback(PlayerError::Stream(err.to_string()));
+                        return;
+                    }
+                }
+            },
+        )))
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+/// -3 dB, the ITU-R BS.775 weight for center and surround channels
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Layout a file is mixed to before it is sent to the output device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelMode {
+    /// Keep the file's channels when the device supports them, otherwise
+    /// mix to whatever channel count the device offers
+    #[default]
+    Auto,
+    Stereo,
+    Mono,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 3] =
+        [ChannelMode::Auto, ChannelMode::Stereo, ChannelMode::Mono];
+
+    /// Channel count to request from the output for a file with `channels`
+    pub fn target_channels(&self, channels: u16) -> u16 {
+        match self {
+            ChannelMode::Auto => channels,
+            ChannelMode::Stereo => 2,
+            ChannelMode::Mono => 1,
+        }
+    }
+}
+
+impl fmt::Display for ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelMode::Auto => write!(f, "Auto"),
+            ChannelMode::Stereo => write!(f, "Stereo"),
+            ChannelMode::Mono => write!(f, "Mono"),
+        }
+    }
+}
+
+/// Mixes interleaved frames from one channel count to another with a fixed
+/// gain matrix
+pub struct ChannelMixer {
+    inputs: usize,
+    outputs: usize,
+    /// Row-major `outputs x inputs` gains
+    matrix: Vec<f32>,
+}
+
+impl ChannelMixer {
+    /// Mixes `inputs` channels to `outputs` channels, passing through the
+    /// intermediate `layout` channel count chosen by the user. Returns `None`
+    /// when no mixing is needed.
+    pub fn new(inputs: u16, layout: u16, outputs: u16) -> Option<Self> {
+        if inputs == layout && layout == outputs {
+            return None;
+        }
+
+        let (inputs, layout, outputs) =
+            (inputs as usize, layout as usize, outputs as usize);
+        let to_layout = mix_matrix(inputs, layout);
+        let to_output = mix_matrix(layout, outputs);
+
+        let mut matrix = vec![0f32; outputs * inputs];
+        for output in 0..outputs {
+            for input in 0..inputs {
+                matrix[output * inputs + input] = (0..layout)
+                    .map(|l| {
+                        to_output[output * layout + l]
+                            * to_layout[l * inputs + input]
+                    })
+                    .sum();
+            }
+        }
+
+        Some(Self {
+            inputs,
+            outputs,
+            matrix,
+        })
+    }
+
+    /// Mixes the whole frames in `input` into `output` and returns the number
+    /// of samples written
+    pub fn mix(&self, input: &[f32], output: &mut [f32]) -> usize {
+        let frames =
+            (input.len() / self.inputs).min(output.len() / self.outputs);
+
+        for frame in 0..frames {
+            let input = &input[frame * self.inputs..][..self.inputs];
+            let output = &mut output[frame * self.outputs..][..self.outputs];
+
+            for (channel, sample) in output.iter_mut().enumerate() {
+                *sample = self.matrix[channel * self.inputs..][..self.inputs]
+                    .iter()
+                    .zip(input)
+                    .map(|(gain, sample)| gain * sample)
+                    .sum();
+            }
+        }
+
+        frames * self.outputs
+    }
+}
+
+/// Row-major `outputs x inputs` gain matrix between two channel counts,
+/// assuming the standard WAV channel order
+fn mix_matrix(inputs: usize, outputs: usize) -> Vec<f32> {
+    let mut matrix = vec![0f32; outputs * inputs];
+
+    if inputs == outputs {
+        for channel in 0..inputs {
+            matrix[channel * inputs + channel] = 1.;
+        }
+    } else if outputs == 1 {
+        let stereo = stereo_downmix(inputs);
+        for input in 0..inputs {
+            matrix[input] = 0.5 * (stereo[input] + stereo[inputs + input]);
+        }
+    } else if inputs == 1 {
+        // Mono goes to the front left and right speakers
+        matrix[0] = 1.;
+        matrix[1] = 1.;
+    } else if outputs == 2 || inputs > outputs {
+        // Downmix to stereo and put it on the front left and right speakers
+        matrix[..2 * inputs].copy_from_slice(&stereo_downmix(inputs));
+    } else {
+        // Upmix by leaving the extra output channels silent
+        for channel in 0..inputs {
+            matrix[channel * inputs + channel] = 1.;
+        }
+    }
+
+    matrix
+}
+
+/// Row-major `2 x inputs` ITU-R BS.775 downmix coefficients for the common
+/// WAV layouts. The LFE channel is dropped.
+fn stereo_downmix(inputs: usize) -> Vec<f32> {
+    #[rustfmt::skip]
+    let (left, right): (&[f32], &[f32]) = match inputs {
+        // L R
+        2 => (&[1., 0.], &[0., 1.]),
+        // L R C
+        3 => (&[1., 0., MINUS_3DB], &[0., 1., MINUS_3DB]),
+        // FL FR BL BR
+        4 => (&[1., 0., MINUS_3DB, 0.], &[0., 1., 0., MINUS_3DB]),
+        // L R C Ls Rs
+        5 => (
+            &[1., 0., MINUS_3DB, MINUS_3DB, 0.],
+            &[0., 1., MINUS_3DB, 0., MINUS_3DB],
+        ),
+        // L R C LFE Ls Rs
+        6 => (
+            &[1., 0., MINUS_3DB, 0., MINUS_3DB, 0.],
+            &[0., 1., MINUS_3DB, 0., 0., MINUS_3DB],
+        ),
+        // L R C LFE Cs Ls Rs
+        7 => (
+            &[1., 0., MINUS_3DB, 0., 0.5, MINUS_3DB, 0.],
+            &[0., 1., MINUS_3DB, 0., 0.5, 0., MINUS_3DB],
+        ),
+        // L R C LFE Lb Rb Ls Rs
+        8 => (
+            &[1., 0., MINUS_3DB, 0., MINUS_3DB, 0., MINUS_3DB, 0.],
+            &[0., 1., MINUS_3DB, 0., 0., MINUS_3DB, 0., MINUS_3DB],
+        ),
+        _ => (&[], &[]),
+    };
+
+    if left.len() == inputs {
+        return [left, right].concat();
+    }
+
+    // Unknown layout, alternate channels between left and right
+    let mut matrix = vec![0f32; 2 * inputs];
+    let gain = 1. / inputs.div_ceil(2) as f32;
+    for input in 0..inputs {
+        matrix[(input % 2) * inputs + input] = gain;
+    }
+    matrix
+}
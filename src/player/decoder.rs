@@ -0,0 +1,110 @@
+use super::PlayerError;
+use hound::{SampleFormat, WavReader, WavSpec};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    /// Integer samples of any width, scaled by `1 / 2^(bits - 1)`
+    Int(f32),
+    Float,
+}
+
+/// Reads interleaved samples from a WAV file as f32 in the range [-1, 1],
+/// regardless of the sample format the file was written in
+pub struct Decoder<R> {
+    reader: WavReader<R>,
+    format: Format,
+}
+
+impl Decoder<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, PlayerError> {
+        let reader =
+            WavReader::open(path).map_err(|err| PlayerError::Open {
+                path: path.to_path_buf(),
+                reason: err.to_string(),
+            })?;
+
+        Self::new(reader).map_err(|reason| PlayerError::Open {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+}
+
+impl<R: Read + Seek> Decoder<R> {
+    pub fn new(reader: WavReader<R>) -> Result<Self, String> {
+        let spec = reader.spec();
+        let format = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, bits @ 1..=32) => {
+                Format::Int(1. / (1u64 << (bits - 1)) as f32)
+            }
+            (SampleFormat::Float, 32) => Format::Float,
+            (format, bits) => {
+                return Err(format!(
+                    "unsupported sample format: {} bit {:?}",
+                    bits, format
+                ))
+            }
+        };
+
+        Ok(Self { reader, format })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.reader.spec()
+    }
+
+    /// Level of the largest positive sample, which integer formats decode to
+    /// one step short of 1
+    pub fn full_scale(&self) -> f32 {
+        match self.format {
+            Format::Int(scale) => 1. - scale,
+            Format::Float => 1.,
+        }
+    }
+
+    /// Duration of the file in frames
+    pub fn duration(&self) -> u32 {
+        self.reader.duration()
+    }
+
+    /// Fills `buffer` with the next interleaved samples and returns how many
+    /// were written, which is less than `buffer.len()` at the end of the file
+    pub fn read(&mut self, buffer: &mut [f32]) -> Result<usize, PlayerError> {
+        match self.format {
+            Format::Int(scale) => {
+                fill(self.reader.samples::<i32>(), buffer, |sample| {
+                    sample as f32 * scale
+                })
+            }
+            Format::Float => {
+                fill(self.reader.samples::<f32>(), buffer, |sample| sample)
+            }
+        }
+    }
+
+    /// Moves the read position to `frame`
+    pub fn seek(&mut self, frame: u64) -> Result<(), PlayerError> {
+        self.reader
+            .seek(frame.min(u32::MAX as u64) as u32)
+            .map_err(|err| PlayerError::Seek(err.to_string()))
+    }
+}
+
+fn fill<S>(
+    samples: impl Iterator<Item = hound::Result<S>>,
+    buffer: &mut [f32],
+    convert: impl Fn(S) -> f32,
+) -> Result<usize, PlayerError> {
+    let mut written = 0;
+    for (slot, sample) in buffer.iter_mut().zip(samples) {
+        *slot = convert(
+            sample.map_err(|err| PlayerError::Decode(err.to_string()))?,
+        );
+        written += 1;
+    }
+
+    Ok(written)
+}
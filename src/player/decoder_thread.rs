@@ -0,0 +1,173 @@
+use super::channels::ChannelMixer;
+use super::decoder::Decoder;
+use super::event::{EventSender, PlayerEvent};
+use super::output::Segment;
+use super::resampler::Resampler;
+use super::transport::Transport;
+use super::worker::Worker;
+use super::{PlayerError, BUFFER_SIZE};
+use rtrb::Producer;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Decodes a file and converts it to the output's channels and sample rate
+pub struct Source {
+    decoder: Decoder<BufReader<File>>,
+    mixer: Option<ChannelMixer>,
+    resampler: Option<Resampler>,
+    file_channels: usize,
+    output_channels: usize,
+    file_samples: Vec<f32>,
+}
+
+impl Source {
+    pub fn new(
+        decoder: Decoder<BufReader<File>>,
+        mixer: Option<ChannelMixer>,
+        resampler: Option<Resampler>,
+        output_channels: usize,
+    ) -> Self {
+        Self {
+            file_channels: decoder.spec().channels as usize,
+            decoder,
+            mixer,
+            resampler,
+            output_channels,
+            file_samples: vec![],
+        }
+    }
+
+    /// Seeks to a frame of the file
+    fn seek(&mut self, frame: u64) -> Result<(), PlayerError> {
+        self.decoder.seek(frame)?;
+        match self.resampler {
+            Some(ref mut resampler) => resampler.reset(),
+            None => Ok(()),
+        }
+    }
+
+    /// Fills `buffer` with whole output frames and returns the number of
+    /// samples written, which is less than `buffer.len()` at the end of the
+    /// file
+    fn read(&mut self, buffer: &mut [f32]) -> Result<usize, PlayerError> {
+        let Self {
+            decoder,
+            mixer,
+            resampler,
+            file_channels,
+            output_channels,
+            file_samples,
+        } = self;
+
+        let mut read_input = |buffer: &mut [f32]| match mixer {
+            Some(ref mixer) => {
+                let len = buffer.len() / *output_channels * *file_channels;
+                if file_samples.len() < len {
+                    file_samples.resize(len, 0f32);
+                }
+                let read = decoder.read(&mut file_samples[..len])?;
+                Ok(mixer.mix(&file_samples[..read], buffer))
+            }
+            None => decoder.read(buffer),
+        };
+
+        match resampler {
+            Some(resampler) => resampler.read(buffer, read_input),
+            None => read_input(buffer),
+        }
+    }
+}
+
+/// Spawns the thread that keeps the input ring buffer of the output filled.
+/// While the buffer is full it sleeps, looking for room every
+/// `poll_interval` while the output plays. Once the end of the file was
+/// played, or while paused, it sleeps until seeks, playback or shutdown wake
+/// it up. Since the callback can't send events itself, this thread also
+/// reports the end of the file and underruns the callback flagged.
+pub fn spawn(
+    mut source: Source,
+    mut input: Producer<f32>,
+    mut segments: Producer<Segment>,
+    transport: Arc<Transport>,
+    events: EventSender,
+    poll_interval: Duration,
+    mut on_error: impl FnMut(PlayerError) + Send + 'static,
+) -> Worker {
+    Worker::spawn(move |signals| {
+        let frame_size = source.output_channels;
+        let mut samples = vec![0f32; BUFFER_SIZE];
+        let mut generation = transport.generation();
+        let mut finished = false;
+        let mut underruns = transport.underruns();
+        // Whether the end of the file was played for `generation`
+        let mut ended = false;
+
+        while !signals.is_shutdown() {
+            if transport.take_ended() {
+                ended = true;
+                let _ = events.unbounded_send(PlayerEvent::TrackEnded);
+            }
+            if transport.underruns() != underruns {
+                underruns = transport.underruns();
+                let _ = events.unbounded_send(PlayerEvent::BufferUnderrun);
+            }
+
+            let current = transport.generation();
+            if current != generation {
+                generation = current;
+                finished = false;
+                ended = false;
+                if let Err(err) = source.seek(transport.start_frame()) {
+                    on_error(err);
+                    return;
+                }
+            }
+
+            // Only write whole frames so channels stay interleaved
+            if finished || input.slots() < frame_size || segments.is_full() {
+                if ended {
+                    // Nothing is left to report until the next seek
+                    thread::park();
+                } else {
+                    signals.park(poll_interval);
+                }
+                continue;
+            }
+
+            let len = input.slots().min(BUFFER_SIZE) / frame_size * frame_size;
+            let read = match source.read(&mut samples[..len]) {
+                Ok(read) => read,
+                Err(err) => {
+                    on_error(err);
+                    return;
+                }
+            };
+            finished = read < len;
+            if read == 0 {
+                transport.finish(generation);
+                continue;
+            }
+
+            let mut chunk = input.write_chunk(read).unwrap();
+            let (first, second) = chunk.as_mut_slices();
+            let mid = first.len();
+
+            first.copy_from_slice(&samples[..mid]);
+            second.copy_from_slice(&samples[mid..read]);
+
+            chunk.commit_all();
+            // Tag the samples so the callback drops them if a seek
+            // happened while they were decoded
+            let _ = segments.push(Segment {
+                generation,
+                len: read,
+            });
+            if finished {
+                transport.finish(generation);
+            }
+        }
+    })
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub enum PlayerError {
+    /// The file could not be opened or is not a valid WAV file
+    Open {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Reading samples from the opened file failed
+    Decode(String),
+    Seek(String),
+    NoOutputDevice,
+    /// The selected output device is no longer available
+    DeviceNotFound(String),
+    /// The output device can't play the requested stream configuration
+    UnsupportedConfig {
+        sample_rate: u32,
+        channels: u16,
+    },
+    /// The resampler between the file and the device could not be set up
+    Resample(String),
+    BuildStream(String),
+    PlayStream(String),
+    PauseStream(String),
+    /// The device of a running stream was disconnected
+    DeviceDisconnected,
+    /// An error reported by a running stream
+    Stream(String),
+    /// A report could not be written
+    Export {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl PlayerError {
+    /// Whether the error means the output stream stopped working
+    pub fn is_stream_error(&self) -> bool {
+        matches!(
+            self,
+            PlayerError::DeviceDisconnected | PlayerError::Stream(_)
+        )
+    }
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::Open { path, reason } => {
+                write!(f, "Failed to open {}: {}", path.display(), reason)
+            }
+            PlayerError::Decode(reason) => {
+                write!(f, "Failed to read sample: {}", reason)
+            }
+            PlayerError::Seek(reason) => {
+                write!(f, "Failed to seek: {}", reason)
+            }
+            PlayerError::NoOutputDevice => {
+                write!(f, "No output device available")
+            }
+            PlayerError::DeviceNotFound(device) => {
+                write!(f, "Output device {} not found", device)
+            }
+            PlayerError::UnsupportedConfig {
+                sample_rate,
+                channels,
+            } => write!(
+                f,
+                "Output device does not support {} Hz with {} channel(s)",
+                sample_rate, channels
+            ),
+            PlayerError::Resample(reason) => {
+                write!(f, "Failed to resample: {}", reason)
+            }
+            PlayerError::BuildStream(reason) => {
+                write!(f, "Building output stream failed: {}", reason)
+            }
+            PlayerError::PlayStream(reason) => {
+                write!(f, "Failed to start playback: {}", reason)
+            }
+            PlayerError::PauseStream(reason) => {
+                write!(f, "Failed to pause playback: {}", reason)
+            }
+            PlayerError::DeviceDisconnected => {
+                write!(f, "Output device was disconnected")
+            }
+            PlayerError::Stream(reason) => {
+                write!(f, "Output stream error: {}", reason)
+            }
+            PlayerError::Export { path, reason } => {
+                write!(f, "Failed to write {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlayerError {}
@@ -0,0 +1,52 @@
+use super::loudness::LoudnessReport;
+use super::PlayerError;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use hound::WavSpec;
+use std::sync::{Arc, Mutex};
+
+/// Notifications about the state of playback, sent by `Player` and its
+/// threads as things happen
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// A file was loaded and is ready to play
+    Loaded { spec: WavSpec },
+    /// The position jumped because of a seek
+    PositionChanged { seconds: f32 },
+    /// The output played all audio of the loaded file
+    TrackEnded,
+    /// The output ran out of decoded audio and played silence
+    BufferUnderrun,
+    /// The output stream failed
+    StreamError(PlayerError),
+    /// The loudness of the loaded file was scanned, for the normalization or
+    /// because it was asked for with `Player::scan_loudness`
+    LoudnessScanned(LoudnessReport),
+    /// Scanning the loudness of the loaded file failed
+    ScanFailed(PlayerError),
+}
+
+pub type EventSender = UnboundedSender<PlayerEvent>;
+
+/// Receiving end of the events of a `Player`. Clones share the same queue, so
+/// every event is received once.
+#[derive(Clone)]
+pub struct PlayerEvents {
+    receiver: Arc<Mutex<UnboundedReceiver<PlayerEvent>>>,
+}
+
+impl PlayerEvents {
+    pub(super) fn new(receiver: UnboundedReceiver<PlayerEvent>) -> Self {
+        Self {
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    /// Waits for the next event. Returns `None` once the player is dropped.
+    pub async fn next(&self) -> Option<PlayerEvent> {
+        futures::future::poll_fn(|cx| {
+            self.receiver.lock().unwrap().poll_next_unpin(cx)
+        })
+        .await
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Time constant of the ramp to a new gain
+const RAMP_TIME: Duration = Duration::from_millis(10);
+
+/// Difference below which the ramp jumps to the new gain
+const RAMP_EPSILON: f32 = 1e-5;
+
+/// Linear gain set by `Player` and applied by the audio callback, stored as
+/// the bits of an `f32` so it can be read without locking
+pub struct Gain {
+    bits: AtomicU32,
+}
+
+impl Gain {
+    pub fn new(gain: f32) -> Self {
+        Self {
+            bits: AtomicU32::new(gain.to_bits()),
+        }
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, gain: f32) {
+        self.bits.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_db(&self, db: f32) {
+        self.set(10f32.powf(db / 20.));
+    }
+}
+
+/// Gain applied by the audio callback, which follows changes of its target
+/// sample by sample so they don't click
+pub struct GainRamp {
+    current: f32,
+    /// Per frame factor of the remaining distance to the target
+    coefficient: f32,
+}
+
+impl GainRamp {
+    pub fn new(sample_rate: u32, gain: f32) -> Self {
+        Self {
+            current: gain,
+            coefficient: (-1. / (RAMP_TIME.as_secs_f32() * sample_rate as f32))
+                .exp(),
+        }
+    }
+
+    /// Applies the gain to interleaved frames while moving it to `target`
+    pub fn apply(
+        &mut self,
+        target: f32,
+        samples: &mut [f32],
+        frame_size: usize,
+    ) {
+        if self.current == target {
+            if target != 1. {
+                samples.iter_mut().for_each(|sample| *sample *= target);
+            }
+            return;
+        }
+
+        for frame in samples.chunks_exact_mut(frame_size) {
+            self.current = target + (self.current - target) * self.coefficient;
+            if (self.current - target).abs() < RAMP_EPSILON {
+                self.current = target;
+            }
+            frame.iter_mut().for_each(|sample| *sample *= self.current);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use super::decoder::Decoder;
+use super::true_peak::TruePeak;
+use super::PlayerError;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Blocks quieter than this in LUFS are left out of the integrated loudness
+/// and the loudness range
+const ABSOLUTE_GATE: f64 = -70.;
+
+/// Relative gate of the integrated loudness in LU
+const INTEGRATED_GATE: f64 = -10.;
+
+/// Relative gate of the loudness range in LU, from EBU Tech 3342
+const RANGE_GATE: f64 = -20.;
+
+/// Loudness resolution of the gating histograms in LU
+const BIN_SIZE: f64 = 0.1;
+
+/// Loudest block the histograms tell apart, louder ones share the last bin
+const MAX_LOUDNESS: f64 = 10.;
+
+/// Length in seconds of the steps momentary and short-term blocks move by
+const STEP: f64 = 0.1;
+
+/// Momentary blocks are 400 ms and short-term blocks 3 s long
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+/// Frames read at once by the offline scan
+const SCAN_FRAMES: usize = 4096;
+
+/// Loudness readings in LUFS, or LU for the range. Readings without enough
+/// audio to measure are minus infinity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Loudness of the last 400 ms
+    pub momentary: f32,
+    /// Loudness of the last 3 s
+    pub short_term: f32,
+    /// Gated loudness of everything measured
+    pub integrated: f32,
+    /// Spread of the short-term loudness, from EBU Tech 3342
+    pub range: f32,
+}
+
+impl Loudness {
+    pub fn empty() -> Self {
+        Self {
+            momentary: f32::NEG_INFINITY,
+            short_term: f32::NEG_INFINITY,
+            integrated: f32::NEG_INFINITY,
+            range: 0.,
+        }
+    }
+}
+
+/// Loudness of a whole file, as measured by `scan`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessReport {
+    pub path: PathBuf,
+    /// Length of the file in seconds
+    pub duration: f64,
+    /// Gated loudness of the whole file in LUFS
+    pub integrated: f32,
+    /// Loudness range in LU
+    pub range: f32,
+    /// Loudest momentary block in LUFS
+    pub max_momentary: f32,
+    /// Loudest short-term block in LUFS
+    pub max_short_term: f32,
+    /// Highest true peak of all channels in dBTP
+    pub true_peak: f32,
+}
+
+/// File format of an exported report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl LoudnessReport {
+    pub fn to_text(&self) -> String {
+        format!(
+            "File:                {}\n\
+             Duration:            {:.1} s\n\
+             Integrated loudness: {:.1} LUFS\n\
+             Loudness range:      {:.1} LU\n\
+             Max momentary:       {:.1} LUFS\n\
+             Max short-term:      {:.1} LUFS\n\
+             True peak:           {:.1} dBTP\n",
+            self.path.display(),
+            self.duration,
+            self.integrated,
+            self.range,
+            self.max_momentary,
+            self.max_short_term,
+            self.true_peak,
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        // JSON has no infinity, so readings of silence are null
+        let number = |value: f32| {
+            if value.is_finite() {
+                format!("{:.1}", value)
+            } else {
+                "null".to_string()
+            }
+        };
+        let path = self
+            .path
+            .display()
+            .to_string()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+
+        format!(
+            "{{\n  \"file\": \"{}\",\n  \"duration\": {:.3},\n  \
+             \"integrated\": {},\n  \"range\": {},\n  \
+             \"max_momentary\": {},\n  \"max_short_term\": {},\n  \
+             \"true_peak\": {}\n}}\n",
+            path,
+            self.duration,
+            number(self.integrated),
+            number(self.range),
+            number(self.max_momentary),
+            number(self.max_short_term),
+            number(self.true_peak),
+        )
+    }
+
+    /// Writes the report next to the measured file, as `<name>.loudness.txt`
+    /// or `<name>.loudness.json`, and returns where it was written
+    pub fn export(&self, format: ReportFormat) -> Result<PathBuf, PlayerError> {
+        let (extension, contents) = match format {
+            ReportFormat::Text => ("loudness.txt", self.to_text()),
+            ReportFormat::Json => ("loudness.json", self.to_json()),
+        };
+        let path = self.path.with_extension(extension);
+        fs::write(&path, contents).map_err(|err| PlayerError::Export {
+            path: path.clone(),
+            reason: err.to_string(),
+        })?;
+        Ok(path)
+    }
+}
+
+/// Measures the loudness of a whole file as fast as it can be read
+pub fn scan(path: &Path) -> Result<LoudnessReport, PlayerError> {
+    let mut decoder = Decoder::open(path)?;
+    let spec = decoder.spec();
+    let channels = spec.channels as usize;
+    let mut meter = LoudnessMeter::new(spec.sample_rate, channels);
+    let mut true_peaks: Vec<TruePeak> =
+        (0..channels).map(|_| TruePeak::new()).collect();
+    let mut true_peak = 0f32;
+
+    let mut buffer = vec![0f32; SCAN_FRAMES * channels];
+    loop {
+        let len = decoder.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        let samples = &buffer[..len - len % channels];
+        meter.push(samples, &[]);
+        for (i, sample) in samples.iter().enumerate() {
+            true_peak = true_peak.max(true_peaks[i % channels].push(*sample));
+        }
+    }
+
+    let loudness = meter.loudness();
+    Ok(LoudnessReport {
+        path: path.to_path_buf(),
+        duration: decoder.duration() as f64 / spec.sample_rate as f64,
+        integrated: loudness.integrated,
+        range: loudness.range,
+        max_momentary: meter.max_momentary(),
+        max_short_term: meter.max_short_term(),
+        true_peak: 20. * true_peak.log10(),
+    })
+}
+
+/// Loudness meter after ITU-R BS.1770 and EBU R 128
+pub struct LoudnessMeter {
+    filters: Vec<KWeighting>,
+    /// Weight of each channel in the sum
+    weights: Vec<f64>,
+    step_frames: usize,
+    /// Frames and weighted sum of squares of the step being measured
+    frames: usize,
+    sum: f64,
+    /// Mean square of the last steps, newest last
+    steps: VecDeque<f64>,
+    /// Momentary blocks, for the integrated loudness
+    momentary: Histogram,
+    /// Short-term blocks, for the loudness range
+    short_term: Histogram,
+    max_momentary: f64,
+    max_short_term: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            filters: (0..channels)
+                .map(|_| KWeighting::new(sample_rate))
+                .collect(),
+            weights: channel_weights(channels),
+            step_frames: ((sample_rate as f64 * STEP).round() as usize).max(1),
+            frames: 0,
+            sum: 0.,
+            steps: VecDeque::with_capacity(SHORT_TERM_STEPS),
+            momentary: Histogram::new(),
+            short_term: Histogram::new(),
+            max_momentary: 0.,
+            max_short_term: 0.,
+        }
+    }
+
+    /// Starts measuring again
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.frames = 0;
+        self.sum = 0.;
+        self.steps.clear();
+        self.momentary = Histogram::new();
+        self.short_term = Histogram::new();
+        self.max_momentary = 0.;
+        self.max_short_term = 0.;
+    }
+
+    /// Adds the interleaved frames in `first` and then `second` to the
+    /// measurements
+    pub fn push(&mut self, first: &[f32], second: &[f32]) {
+        let channels = self.filters.len();
+        for (i, sample) in first.iter().chain(second).enumerate() {
+            let channel = i % channels;
+            let filtered = self.filters[channel].process(*sample as f64);
+            self.sum += self.weights[channel] * filtered * filtered;
+
+            if channel == channels - 1 {
+                self.frames += 1;
+                if self.frames == self.step_frames {
+                    self.end_step();
+                }
+            }
+        }
+    }
+
+    fn end_step(&mut self) {
+        if self.steps.len() == SHORT_TERM_STEPS {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(self.sum / self.frames as f64);
+        self.frames = 0;
+        self.sum = 0.;
+
+        if self.steps.len() >= MOMENTARY_STEPS {
+            let power = self.mean_square(MOMENTARY_STEPS);
+            self.momentary.add(power);
+            self.max_momentary = self.max_momentary.max(power);
+        }
+        if self.steps.len() >= SHORT_TERM_STEPS {
+            let power = self.mean_square(SHORT_TERM_STEPS);
+            self.short_term.add(power);
+            self.max_short_term = self.max_short_term.max(power);
+        }
+    }
+
+    /// Mean square of the last `steps` steps, where missing steps count as
+    /// silence
+    fn mean_square(&self, steps: usize) -> f64 {
+        self.steps.iter().rev().take(steps).sum::<f64>() / steps as f64
+    }
+
+    pub fn loudness(&self) -> Loudness {
+        Loudness {
+            momentary: to_lufs(self.mean_square(MOMENTARY_STEPS)),
+            short_term: to_lufs(self.mean_square(SHORT_TERM_STEPS)),
+            integrated: self.momentary.integrated(),
+            range: self.short_term.range(),
+        }
+    }
+
+    pub fn max_momentary(&self) -> f32 {
+        to_lufs(self.max_momentary)
+    }
+
+    pub fn max_short_term(&self) -> f32 {
+        to_lufs(self.max_short_term)
+    }
+}
+
+/// Weights from BS.1770 for the common layouts: 1.41 for the surround
+/// channels of 5.0 and 5.1, nothing for the LFE of 5.1 and 1 otherwise
+fn channel_weights(channels: usize) -> Vec<f64> {
+    (0..channels)
+        .map(|channel| match (channels, channel) {
+            (5, 3 | 4) | (6, 4 | 5) => 1.41,
+            (6, 3) => 0.,
+            _ => 1.,
+        })
+        .collect()
+}
+
+fn to_lufs(power: f64) -> f32 {
+    if power > 0. {
+        (-0.691 + 10. * power.log10()) as f32
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Counts and powers of blocks in bins of `BIN_SIZE` LU above the absolute
+/// gate, so the gated measurements don't have to keep every block
+struct Histogram {
+    counts: Vec<u64>,
+    powers: Vec<f64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        let bins = ((MAX_LOUDNESS - ABSOLUTE_GATE) / BIN_SIZE) as usize;
+        Self {
+            counts: vec![0; bins],
+            powers: vec![0.; bins],
+        }
+    }
+
+    fn add(&mut self, power: f64) {
+        let lufs = to_lufs(power) as f64;
+        if lufs < ABSOLUTE_GATE {
+            return;
+        }
+        let bin = (((lufs - ABSOLUTE_GATE) / BIN_SIZE) as usize)
+            .min(self.counts.len() - 1);
+        self.counts[bin] += 1;
+        self.powers[bin] += power;
+    }
+
+    /// First bin of the blocks at most `gate` LU below the mean power of all
+    /// blocks
+    fn relative_gate(&self, gate: f64) -> Option<usize> {
+        let count = self.counts.iter().sum::<u64>();
+        if count == 0 {
+            return None;
+        }
+        let mean = self.powers.iter().sum::<f64>() / count as f64;
+        let threshold = to_lufs(mean) as f64 + gate;
+        Some(((threshold - ABSOLUTE_GATE) / BIN_SIZE).ceil().max(0.) as usize)
+    }
+
+    fn integrated(&self) -> f32 {
+        let Some(first) = self.relative_gate(INTEGRATED_GATE) else {
+            return f32::NEG_INFINITY;
+        };
+        let count = self.counts[first..].iter().sum::<u64>();
+        if count == 0 {
+            return f32::NEG_INFINITY;
+        }
+        to_lufs(self.powers[first..].iter().sum::<f64>() / count as f64)
+    }
+
+    /// Distance between the 10th and 95th percentile of the gated blocks
+    fn range(&self) -> f32 {
+        let Some(first) = self.relative_gate(RANGE_GATE) else {
+            return 0.;
+        };
+        let counts = &self.counts[first.min(self.counts.len())..];
+        let count = counts.iter().sum::<u64>();
+        if count == 0 {
+            return 0.;
+        }
+
+        let percentile = |fraction: f64| {
+            let rank = ((count - 1) as f64 * fraction).round() as u64;
+            let mut seen = 0;
+            for (bin, bin_count) in counts.iter().enumerate() {
+                seen += bin_count;
+                if seen > rank {
+                    return (first + bin) as f64;
+                }
+            }
+            (first + counts.len() - 1) as f64
+        };
+
+        ((percentile(0.95) - percentile(0.1)) * BIN_SIZE) as f32
+    }
+}
+
+/// The K-weighting pre-filter of BS.1770, a high shelf followed by a high
+/// pass, with coefficients derived for any sample rate as in libebur128
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1. + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2. * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1. + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1., -2., 1.],
+            [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        );
+
+        Self {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.state = [0.; 2];
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.stages
+            .iter_mut()
+            .fold(sample, |sample, stage| stage.process(sample))
+    }
+}
+
+/// Second order IIR filter in transposed direct form II
+struct Biquad {
+    b: [f64; 3],
+    /// Feedback coefficients `a1` and `a2`, with `a0` normalised to 1
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            state: [0.; 2],
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
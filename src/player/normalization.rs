@@ -0,0 +1,243 @@
+use super::event::{EventSender, PlayerEvent};
+use super::gain::Gain;
+use super::loudness::{self, LoudnessReport};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+/// Highest true peak in dBTP the normalization gain may raise a file to
+const PEAK_CEILING: f32 = -1.;
+
+/// How loaded files are brought to the same loudness
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizationConfig {
+    pub enabled: bool,
+    /// Integrated loudness in LUFS files are played at, like -14 of the
+    /// streaming services or -23 of EBU R 128 broadcast
+    pub target: f32,
+}
+
+impl NormalizationConfig {
+    pub const MIN_TARGET: f32 = -31.;
+    pub const MAX_TARGET: f32 = -5.;
+
+    /// Gain in dB that brings a file with `report` to the target loudness,
+    /// lowered as far as needed to keep its true peak below `PEAK_CEILING`
+    pub fn gain(&self, report: &LoudnessReport) -> f32 {
+        if !report.integrated.is_finite() {
+            // Silence stays silent at any gain
+            return 0.;
+        }
+        (self.target - report.integrated).min(PEAK_CEILING - report.true_peak)
+    }
+
+    pub fn validated(self) -> Self {
+        Self {
+            target: self.target.clamp(Self::MIN_TARGET, Self::MAX_TARGET),
+            ..self
+        }
+    }
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: -14.,
+        }
+    }
+}
+
+/// Loaded file as it was when it was loaded, so a scan of an older version
+/// isn't used after the file changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileId {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileId {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+}
+
+struct State {
+    config: NormalizationConfig,
+    file: Option<FileId>,
+    /// Scans of every file scanned so far, by path
+    scans: HashMap<PathBuf, (Option<SystemTime>, LoudnessReport)>,
+    /// Paths that are being scanned right now
+    scanning: HashSet<PathBuf>,
+    /// Gain in dB applied to the loaded file
+    gain: Option<f32>,
+}
+
+impl State {
+    /// Scan of `file`, unless it changed since
+    fn report(&self, file: &FileId) -> Option<&LoudnessReport> {
+        match self.scans.get(&file.path) {
+            Some((modified, report)) if *modified == file.modified => {
+                Some(report)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    gain: Arc<Gain>,
+    events: EventSender,
+}
+
+/// Plays every file at the same integrated loudness. Files are scanned on a
+/// thread of their own the first time they are loaded, and play at unity
+/// gain until it is done. The scans are kept per file, so they also serve
+/// the loudness reports.
+pub struct Normalizer {
+    shared: Arc<Shared>,
+}
+
+impl Normalizer {
+    /// Normalizes through `gain`, which the audio callback applies
+    pub fn new(gain: Arc<Gain>, events: EventSender) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    config: NormalizationConfig::default(),
+                    file: None,
+                    scans: HashMap::new(),
+                    scanning: HashSet::new(),
+                    gain: None,
+                }),
+                gain,
+                events,
+            }),
+        }
+    }
+
+    /// Switches to the gain of a newly loaded file, or unity gain without a
+    /// file
+    pub fn load(&self, path: Option<&Path>) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.file = path.map(FileId::new);
+        update(&self.shared, &mut state);
+    }
+
+    pub fn set_config(&self, config: NormalizationConfig) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.config = config.validated();
+        update(&self.shared, &mut state);
+    }
+
+    pub fn config(&self) -> NormalizationConfig {
+        self.shared.state.lock().unwrap().config
+    }
+
+    /// Gain in dB applied to the loaded file, if it is normalized yet
+    pub fn gain(&self) -> Option<f32> {
+        self.shared.state.lock().unwrap().gain
+    }
+
+    /// Scan of the loaded file, if it is done
+    pub fn report(&self) -> Option<LoudnessReport> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .file
+            .as_ref()
+            .and_then(|file| state.report(file))
+            .cloned()
+    }
+
+    /// Reports the scan of the loaded file through
+    /// `PlayerEvent::LoudnessScanned`, scanning it first if that wasn't done
+    /// yet
+    pub fn scan(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        let Some(file) = state.file.clone() else {
+            return;
+        };
+        match state.report(&file) {
+            Some(report) => {
+                let _ = self.shared.events.unbounded_send(
+                    PlayerEvent::LoudnessScanned(report.clone()),
+                );
+            }
+            None => start_scan(&self.shared, &mut state, file),
+        }
+    }
+
+    /// Whether the loaded file is being scanned
+    pub fn is_scanning(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .file
+            .as_ref()
+            .is_some_and(|file| state.scanning.contains(&file.path))
+    }
+}
+
+/// Applies the gain of the loaded file if its scan is known, and starts the
+/// scan if it isn't
+fn update(shared: &Arc<Shared>, state: &mut State) {
+    state.gain = None;
+    let file = match state.file {
+        Some(ref file) if state.config.enabled => file.clone(),
+        _ => {
+            shared.gain.set(1.);
+            return;
+        }
+    };
+
+    match state.report(&file) {
+        Some(report) => {
+            let gain = state.config.gain(report);
+            shared.gain.set_db(gain);
+            state.gain = Some(gain);
+        }
+        None => {
+            shared.gain.set(1.);
+            start_scan(shared, state, file);
+        }
+    }
+}
+
+/// Scans `file` on a thread of its own, unless it is being scanned already
+fn start_scan(shared: &Arc<Shared>, state: &mut State, file: FileId) {
+    if state.scanning.insert(file.path.clone()) {
+        let shared = shared.clone();
+        thread::spawn(move || scan(&shared, file));
+    }
+}
+
+fn scan(shared: &Arc<Shared>, file: FileId) {
+    let result = loudness::scan(&file.path);
+
+    let mut state = shared.state.lock().unwrap();
+    state.scanning.remove(&file.path);
+    // The result is kept even if another file was loaded in the meantime,
+    // but only reported for the loaded one
+    let event = match result {
+        Ok(report) => {
+            state
+                .scans
+                .insert(file.path.clone(), (file.modified, report.clone()));
+            update(shared, &mut state);
+            PlayerEvent::LoudnessScanned(report)
+        }
+        Err(err) => PlayerEvent::ScanFailed(err),
+    };
+
+    if state.file.as_ref() == Some(&file) {
+        let _ = shared.events.unbounded_send(event);
+    }
+}
@@ -0,0 +1,232 @@
+use super::backend::CallbackInfo;
+use super::gain::{Gain, GainRamp};
+use super::transport::Transport;
+use rtrb::{Consumer, Producer};
+use std::sync::Arc;
+
+/// Marks the next `len` samples in the input ring buffer as decoded for
+/// `generation`
+#[derive(Debug, Clone, Copy)]
+pub struct Segment {
+    pub generation: u64,
+    pub len: usize,
+}
+
+/// Consuming end of the audio decoded by the decoder thread
+pub struct Input {
+    pub samples: Consumer<f32>,
+    pub segments: Consumer<Segment>,
+}
+
+/// Producing end of the copy of the output the analysis thread reads
+pub struct Tap {
+    pub samples: Producer<f32>,
+}
+
+/// Gains applied to everything the callback plays, set from other threads
+#[derive(Clone)]
+pub struct Gains {
+    pub normalization: Arc<Gain>,
+    pub volume: Arc<Gain>,
+}
+
+impl Gains {
+    fn get(&self) -> f32 {
+        self.normalization.get() * self.volume.get()
+    }
+}
+
+/// State of the audio callback, which moves decoded samples from the input
+/// ring buffer to the analysis ring buffer and, with the gains applied, to
+/// the output device. Runs on the real-time audio thread, so it must not
+/// allocate, lock or do I/O. Problems are reported through the counters in
+/// `Transport` instead.
+pub struct Output {
+    frame_size: usize,
+    sample_rate: f64,
+    input: Input,
+    tap: Tap,
+    transport: Arc<Transport>,
+    gains: Gains,
+    ramp: GainRamp,
+    /// Generation of the audio that should be heard
+    generation: u64,
+    segment: Segment,
+    fade_out: u64,
+    fade_in: u64,
+    /// Whether audio of this generation was played yet. Running out before
+    /// that is waiting for a seek to be decoded rather than an underrun.
+    started: bool,
+    /// Whether the end of the file was reached in this generation
+    ended: bool,
+}
+
+impl Output {
+    pub fn new(
+        frame_size: usize,
+        sample_rate: u32,
+        input: Input,
+        tap: Tap,
+        transport: Arc<Transport>,
+        gains: Gains,
+    ) -> Self {
+        Self {
+            frame_size,
+            sample_rate: sample_rate as f64,
+            input,
+            tap,
+            ramp: GainRamp::new(sample_rate, gains.get()),
+            gains,
+            generation: transport.generation(),
+            transport,
+            segment: Segment {
+                generation: 0,
+                len: 0,
+            },
+            fade_out: 0,
+            fade_in: 0,
+            started: false,
+            ended: false,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], info: &CallbackInfo) {
+        self.transport.set_latency(
+            (info.latency.as_secs_f64() * self.sample_rate).round() as u64,
+        );
+
+        let generation = self.transport.generation();
+        if generation != self.generation {
+            // Fade out what is still buffered from before the seek, then fade
+            // in the audio at the new position
+            self.generation = generation;
+            self.fade_out = self.transport.fade_frames();
+            self.fade_in = self.fade_out;
+            self.transport.restart();
+            self.started = false;
+            self.ended = false;
+        }
+        // Checked before looking at the segments, since the decoder thread
+        // marks the end after pushing the last one
+        let finished = self.transport.is_finished(self.generation);
+
+        let frames = samples.len() / self.frame_size;
+        let mut frame = 0;
+        while frame < frames {
+            if self.segment.len == 0 {
+                match self.input.segments.pop() {
+                    Ok(segment) => self.segment = segment,
+                    Err(_) => {
+                        // Before the first segment of a generation arrives,
+                        // a seek or `play` is still being decoded, which is
+                        // neither an underrun nor the end
+                        if finished && !self.ended {
+                            self.ended = true;
+                            self.transport.end();
+                        } else if !finished && self.started {
+                            self.transport.underrun();
+                        }
+                        break;
+                    }
+                }
+            }
+
+            let stale = self.segment.generation != self.generation;
+            if stale && self.fade_out == 0 {
+                self.skip_segment();
+                continue;
+            }
+
+            let mut count =
+                (self.segment.len / self.frame_size).min(frames - frame);
+            if stale {
+                count = count.min(self.fade_out as usize);
+            }
+
+            let output = &mut samples[frame * self.frame_size..]
+                [..count * self.frame_size];
+            if self.read(output, stale).is_err() {
+                break;
+            }
+
+            if !stale {
+                self.transport.advance(count as u64);
+                self.started = true;
+            }
+            self.segment.len -= count * self.frame_size;
+            frame += count;
+        }
+        samples[frame * self.frame_size..].fill(0f32);
+
+        // The analysis measures the file itself, so the meters don't move
+        // with the normalization and the volume
+        self.write_analysis(samples);
+        self.ramp.apply(
+            self.gains.get(),
+            &mut samples[..frame * self.frame_size],
+            self.frame_size,
+        );
+    }
+
+    /// Copies the next samples of the current segment into `output`, applying
+    /// the seek fades
+    fn read(&mut self, output: &mut [f32], stale: bool) -> Result<(), ()> {
+        let chunk = self
+            .input
+            .samples
+            .read_chunk(output.len())
+            .map_err(|_| ())?;
+        let (first, second) = chunk.as_slices();
+        output[..first.len()].copy_from_slice(first);
+        output[first.len()..].copy_from_slice(second);
+        chunk.commit_all();
+
+        if !stale && self.fade_in == 0 {
+            return Ok(());
+        }
+
+        let fade_frames = self.transport.fade_frames().max(1) as f32;
+        for frame in output.chunks_exact_mut(self.frame_size) {
+            let gain = if stale {
+                self.fade_out -= 1;
+                self.fade_out as f32 / fade_frames
+            } else if self.fade_in > 0 {
+                self.fade_in -= 1;
+                1. - self.fade_in as f32 / fade_frames
+            } else {
+                1.
+            };
+            frame.iter_mut().for_each(|sample| *sample *= gain);
+        }
+
+        Ok(())
+    }
+
+    fn skip_segment(&mut self) {
+        if let Ok(chunk) = self.input.samples.read_chunk(self.segment.len) {
+            chunk.commit_all();
+        }
+        self.segment.len = 0;
+    }
+
+    fn write_analysis(&mut self, samples: &[f32]) {
+        // Only whole frames, so the analysis can deinterleave them
+        let write_slots = samples.len().min(self.tap.samples.slots())
+            / self.frame_size
+            * self.frame_size;
+        if write_slots != samples.len() {
+            // The analysis isn't keeping up, drop what doesn't fit
+            self.transport.overrun();
+        }
+
+        let Ok(mut chunk) = self.tap.samples.write_chunk(write_slots) else {
+            return;
+        };
+        let (first, second) = chunk.as_mut_slices();
+        let mid = first.len();
+
+        first.copy_from_slice(&samples[..mid]);
+        second.copy_from_slice(&samples[mid..write_slots]);
+        chunk.commit_all();
+    }
+}
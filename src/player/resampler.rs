@@ -0,0 +1,230 @@
+use super::PlayerError;
+use rubato::{
+    FastFixedIn, PolynomialDegree, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, VecResampler, WindowFunction,
+};
+
+/// Number of input frames converted per resampler pass
+const CHUNK_FRAMES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    /// Linear interpolation, cheapest but audibly aliases on high content
+    Low,
+    /// Cubic polynomial interpolation
+    #[default]
+    Medium,
+    /// Windowed sinc interpolation
+    High,
+}
+
+/// Converts interleaved samples pulled from an input to another sample rate
+pub struct Resampler {
+    inner: Box<dyn VecResampler<f32>>,
+    channels: usize,
+    quality: ResampleQuality,
+    ratio: f64,
+    input: Vec<f32>,
+    planar_input: Vec<Vec<f32>>,
+    planar_output: Vec<Vec<f32>>,
+    output_frames: usize,
+    output_offset: usize,
+    /// Output frames left to drop to compensate for the resampler delay
+    skip: usize,
+    /// Input frames read so far
+    read_frames: u64,
+    /// Output frames handed out or buffered so far, after the delay
+    output_total: u64,
+    /// Whether the input is exhausted and only padding is resampled
+    input_finished: bool,
+    /// Whether the output frames matching the whole input were produced
+    finished: bool,
+}
+
+impl Resampler {
+    pub fn new(
+        quality: ResampleQuality,
+        from_rate: u32,
+        to_rate: u32,
+        channels: usize,
+    ) -> Result<Self, PlayerError> {
+        let ratio = to_rate as f64 / from_rate as f64;
+        let inner = build_resampler(quality, ratio, channels)?;
+
+        Ok(Self {
+            channels,
+            quality,
+            ratio,
+            input: vec![0f32; inner.input_frames_max() * channels],
+            planar_input: vec![vec![0f32; inner.input_frames_max()]; channels],
+            planar_output: vec![
+                vec![0f32; inner.output_frames_max()];
+                channels
+            ],
+            output_frames: 0,
+            output_offset: 0,
+            skip: output_delay(quality, inner.as_ref()),
+            read_frames: 0,
+            output_total: 0,
+            input_finished: false,
+            finished: false,
+            inner,
+        })
+    }
+
+    /// Fills `buffer` with resampled interleaved samples, pulling from `input`
+    /// whenever more source frames are needed. `input` follows the same
+    /// contract as `Decoder::read`. Returns the number of samples written,
+    /// which is less than `buffer.len()` once the input is exhausted.
+    pub fn read(
+        &mut self,
+        buffer: &mut [f32],
+        mut input: impl FnMut(&mut [f32]) -> Result<usize, PlayerError>,
+    ) -> Result<usize, PlayerError> {
+        let channels = self.channels;
+        let mut written = 0;
+
+        while written + channels <= buffer.len() {
+            if self.output_offset == self.output_frames {
+                if self.finished {
+                    break;
+                }
+                self.process(&mut input)?;
+                continue;
+            }
+
+            let frames = (self.output_frames - self.output_offset)
+                .min((buffer.len() - written) / channels);
+            for frame in 0..frames {
+                for (channel, output) in self.planar_output.iter().enumerate() {
+                    buffer[written + frame * channels + channel] =
+                        output[self.output_offset + frame];
+                }
+            }
+
+            written += frames * channels;
+            self.output_offset += frames;
+        }
+
+        Ok(written)
+    }
+
+    /// Clears all buffered state, e.g. after the input seeked
+    pub fn reset(&mut self) -> Result<(), PlayerError> {
+        // The object safe resampler trait has no reset, so start over
+        self.inner = build_resampler(self.quality, self.ratio, self.channels)?;
+        self.output_frames = 0;
+        self.output_offset = 0;
+        self.skip = output_delay(self.quality, self.inner.as_ref());
+        self.read_frames = 0;
+        self.output_total = 0;
+        self.input_finished = false;
+        self.finished = false;
+
+        Ok(())
+    }
+
+    fn process(
+        &mut self,
+        input: &mut impl FnMut(&mut [f32]) -> Result<usize, PlayerError>,
+    ) -> Result<(), PlayerError> {
+        let frames = self.inner.input_frames_next();
+        let samples = &mut self.input[..frames * self.channels];
+
+        let read = if self.input_finished {
+            0
+        } else {
+            input(samples)?
+        };
+        if read < samples.len() {
+            samples[read..].fill(0f32);
+            self.input_finished = true;
+        }
+        self.read_frames += (read / self.channels) as u64;
+
+        for (channel, planar) in self.planar_input.iter_mut().enumerate() {
+            for (frame, sample) in planar[..frames].iter_mut().enumerate() {
+                *sample = samples[frame * self.channels + channel];
+            }
+        }
+
+        let (_, output_frames) = self
+            .inner
+            .process_into_buffer(
+                &self.planar_input,
+                &mut self.planar_output,
+                None,
+            )
+            .map_err(|err| PlayerError::Resample(err.to_string()))?;
+
+        let skipped = self.skip.min(output_frames);
+        self.skip -= skipped;
+        let mut frames = output_frames - skipped;
+        if self.input_finished {
+            // Zeros are fed until the delayed end of the input comes out,
+            // which may take more than one pass, and the padding after it is
+            // cut off
+            let total = (self.read_frames as f64 * self.ratio).round() as u64;
+            let left = total.saturating_sub(self.output_total);
+            if frames as u64 >= left {
+                frames = left as usize;
+                self.finished = true;
+            }
+        }
+        self.output_total += frames as u64;
+        self.output_offset = skipped;
+        self.output_frames = skipped + frames;
+
+        Ok(())
+    }
+}
+
+/// Output frames the output of `inner` lags behind its input
+fn output_delay(
+    quality: ResampleQuality,
+    inner: &dyn VecResampler<f32>,
+) -> usize {
+    match quality {
+        // The sinc resampler already centers its filter on the input frame
+        // it interpolates, despite the delay it reports
+        ResampleQuality::High => 0,
+        ResampleQuality::Low | ResampleQuality::Medium => inner.output_delay(),
+    }
+}
+
+fn build_resampler(
+    quality: ResampleQuality,
+    ratio: f64,
+    channels: usize,
+) -> Result<Box<dyn VecResampler<f32>>, PlayerError> {
+    match quality {
+        ResampleQuality::Low => FastFixedIn::new(
+            ratio,
+            1.,
+            PolynomialDegree::Linear,
+            CHUNK_FRAMES,
+            channels,
+        )
+        .map(|r| Box::new(r) as Box<dyn VecResampler<f32>>),
+        ResampleQuality::Medium => FastFixedIn::new(
+            ratio,
+            1.,
+            PolynomialDegree::Cubic,
+            CHUNK_FRAMES,
+            channels,
+        )
+        .map(|r| Box::new(r) as Box<dyn VecResampler<f32>>),
+        ResampleQuality::High => {
+            let parameters = SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                oversampling_factor: 256,
+                interpolation: SincInterpolationType::Cubic,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            SincFixedIn::new(ratio, 1., parameters, CHUNK_FRAMES, channels)
+                .map(|r| Box::new(r) as Box<dyn VecResampler<f32>>)
+        }
+    }
+    .map_err(|err| PlayerError::Resample(err.to_string()))
+}
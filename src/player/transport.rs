@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Value of `finished_generation` before the decoder reached the end
+const NOT_FINISHED: u64 = u64::MAX;
+
+/// Playback state shared between `Player`, the decoder thread and the audio
+/// callback. The audible position is derived from the frames the callback
+/// actually handed to the output, not from what was decoded. Since the
+/// callback can't send messages, it also reports through the flags and
+/// counters here.
+pub struct Transport {
+    /// File frame playback started from after the last load or seek
+    start_frame: AtomicU64,
+    /// Output frames consumed by the callback since `start_frame`
+    played_frames: AtomicU64,
+    /// Output frames between the callback and its audio being heard
+    latency_frames: AtomicU64,
+    /// Incremented on every seek. Audio is tagged with the generation it was
+    /// decoded for, so the callback can drop anything decoded before a seek.
+    generation: AtomicU64,
+    /// Length of the fade out and fade in around a seek, in output frames
+    fade_frames: AtomicU64,
+    /// Generation the decoder thread decoded the whole file for
+    finished_generation: AtomicU64,
+    /// Set by the audio callback when it played the last decoded frame
+    ended: AtomicBool,
+    /// Number of callbacks that ran out of decoded audio
+    underruns: AtomicU64,
+    /// Number of callbacks that found the analysis ring buffer full
+    overruns: AtomicU64,
+}
+
+impl Transport {
+    pub fn new(start_frame: u64, fade_frames: u64) -> Self {
+        Self {
+            start_frame: AtomicU64::new(start_frame),
+            played_frames: AtomicU64::new(0),
+            latency_frames: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+            fade_frames: AtomicU64::new(fade_frames),
+            finished_generation: AtomicU64::new(NOT_FINISHED),
+            ended: AtomicBool::new(false),
+            underruns: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
+        }
+    }
+
+    /// Audible position in file frames, where `ratio` is the file sample rate
+    /// divided by the output sample rate
+    pub fn position(&self, ratio: f64) -> u64 {
+        let played = self
+            .played_frames
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.latency_frames.load(Ordering::Relaxed));
+
+        self.start_frame.load(Ordering::Relaxed)
+            + (played as f64 * ratio).round() as u64
+    }
+
+    pub fn seek(&self, frame: u64) {
+        self.start_frame.store(frame, Ordering::Relaxed);
+        self.played_frames.store(0, Ordering::Relaxed);
+        // Publishes the new start frame to the decoder thread
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub fn start_frame(&self) -> u64 {
+        self.start_frame.load(Ordering::Relaxed)
+    }
+
+    /// Called from the audio callback when it starts playing the audio of a
+    /// new generation
+    pub fn restart(&self) {
+        self.played_frames.store(0, Ordering::Relaxed);
+    }
+
+    /// Called from the audio callback after handing `frames` to the output
+    pub fn advance(&self, frames: u64) {
+        self.played_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    pub fn set_latency(&self, frames: u64) {
+        self.latency_frames.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn fade_frames(&self) -> u64 {
+        self.fade_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn set_fade_frames(&self, frames: u64) {
+        self.fade_frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// Called from the decoder thread after it wrote the last frame of the
+    /// file for `generation`
+    pub fn finish(&self, generation: u64) {
+        self.finished_generation
+            .store(generation, Ordering::Release);
+    }
+
+    pub fn is_finished(&self, generation: u64) -> bool {
+        self.finished_generation.load(Ordering::Acquire) == generation
+    }
+
+    pub fn end(&self) {
+        self.ended.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the end of the file was played since the last call
+    pub fn take_ended(&self) -> bool {
+        self.ended.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    pub fn overrun(&self) {
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+}
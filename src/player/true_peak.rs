@@ -0,0 +1,71 @@
+use std::f64::consts::PI;
+
+/// Oversampling factor of the true-peak measurement
+const OVERSAMPLING: usize = 4;
+
+/// Taps of the interpolation filter per phase
+const PHASE_TAPS: usize = 12;
+
+/// Finds the level between the samples of one channel as well, from 4 times
+/// oversampling as in ITU-R BS.1770
+pub struct TruePeak {
+    /// Interpolation filter per phase, taps newest sample first
+    phases: [[f32; PHASE_TAPS]; OVERSAMPLING],
+    /// Latest input samples for the interpolation filter, newest first
+    recent: [f32; PHASE_TAPS],
+}
+
+impl TruePeak {
+    pub fn new() -> Self {
+        Self {
+            phases: interpolation_filter(),
+            recent: [0f32; PHASE_TAPS],
+        }
+    }
+
+    /// Takes the next sample and returns the highest absolute level from it
+    /// up to the next one
+    pub fn push(&mut self, sample: f32) -> f32 {
+        self.recent.copy_within(..PHASE_TAPS - 1, 1);
+        self.recent[0] = sample;
+        self.phases
+            .iter()
+            .map(|taps| {
+                taps.iter()
+                    .zip(&self.recent)
+                    .map(|(tap, sample)| tap * sample)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(sample.abs(), f32::max)
+    }
+}
+
+impl Default for TruePeak {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hann windowed sinc that interpolates `OVERSAMPLING` times with a cutoff at
+/// the original Nyquist frequency, like the true-peak filter of libebur128.
+/// BS.1770 only asks for at least 4 times oversampling with such a filter.
+fn interpolation_filter() -> [[f32; PHASE_TAPS]; OVERSAMPLING] {
+    let len = PHASE_TAPS * OVERSAMPLING;
+    let center = (len - 1) as f64 / 2.;
+    let mut phases = [[0f32; PHASE_TAPS]; OVERSAMPLING];
+
+    for i in 0..len {
+        let x = (i as f64 - center) / OVERSAMPLING as f64;
+        let sinc = if x == 0. {
+            1.
+        } else {
+            (PI * x).sin() / (PI * x)
+        };
+        let window =
+            0.5 - 0.5 * (2. * PI * (i as f64 + 0.5) / len as f64).cos();
+        phases[i % OVERSAMPLING][i / OVERSAMPLING] = (sinc * window) as f32;
+    }
+
+    phases
+}
@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle, Thread};
+use std::time::Duration;
+
+/// Flags a worker is controlled through, checked after every wake up
+pub struct Signals {
+    shutdown: AtomicBool,
+    /// Whether the output plays. The audio callback doesn't wake workers up,
+    /// since that is a system call on the real-time thread, so they poll
+    /// while it consumes or produces audio.
+    playing: AtomicBool,
+}
+
+impl Signals {
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::Acquire)
+    }
+
+    /// Sleeps until the worker is woken up, or for at most `timeout` while
+    /// the output plays
+    pub fn park(&self, timeout: Duration) {
+        if self.is_playing() {
+            thread::park_timeout(timeout);
+        } else {
+            thread::park();
+        }
+    }
+}
+
+/// Background thread that sleeps between rounds of work until it is woken
+/// up, or polls while the output plays. It is shut down and joined when
+/// dropped.
+pub struct Worker {
+    signals: Arc<Signals>,
+    thread: Thread,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Runs `work` on a new thread. It should return soon after shutdown is
+    /// signaled, and starts out as if the output were paused.
+    pub fn spawn(work: impl FnOnce(&Signals) + Send + 'static) -> Self {
+        let signals = Arc::new(Signals {
+            shutdown: AtomicBool::new(false),
+            playing: AtomicBool::new(false),
+        });
+        let thread_signals = signals.clone();
+        let handle = thread::spawn(move || work(&thread_signals));
+
+        Self {
+            signals,
+            thread: handle.thread().clone(),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn wake(&self) {
+        self.thread.unpark();
+    }
+
+    /// Lets the thread know whether the output plays, waking it up so it
+    /// starts or stops polling
+    pub fn set_playing(&self, playing: bool) {
+        self.signals.playing.store(playing, Ordering::Release);
+        self.wake();
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.signals.shutdown.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            self.thread.unpark();
+            let _ = handle.join();
+        }
+    }
+}
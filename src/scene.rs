@@ -1,33 +1,236 @@
+mod axes;
+mod goniometer;
+mod oscilloscope;
+mod spectrogram;
 mod spectrometer;
 
-use iced::mouse::Cursor;
+use iced::mouse::{self, Cursor};
 use iced::time::Duration;
-use iced::widget::canvas::{stroke, Cache, Geometry, Path, Stroke};
-use iced::widget::{canvas, Canvas};
+use iced::widget::canvas::stroke::{self, LineDash};
+use iced::widget::canvas::{Cache, Frame, Geometry, Path, Stroke};
+use iced::widget::{canvas, column, row, Canvas};
 use iced::{Color, Element, Length, Point, Renderer, Theme};
 use iced::{Rectangle, Size};
+use std::fmt;
 
 use crate::message::Message;
-use crate::player::FftSpectrum;
-use spectrometer::Spectrometer;
+use crate::player::{FftSpectrum, Waveform};
+use axes::FrequencyAxis;
+use goniometer::Goniometer;
+use oscilloscope::Oscilloscope;
+pub use oscilloscope::ScopeConfig;
+use spectrogram::Spectrogram;
+pub use spectrogram::{Colormap, ScrollDirection, SpectrogramConfig};
+pub use spectrometer::{Ballistics, SpectrumScale};
+use spectrometer::{Spectrometer, Traces};
 
 const RESOLUTION: usize = 2000;
 
+/// Number of frequency pixels of the spectrogram
+const SPECTROGRAM_ROWS: usize = RESOLUTION / 4;
+
+/// Space for the frequency labels beside the spectrogram
+const AXIS_WIDTH: f32 = 60.;
+const AXIS_HEIGHT: f32 = 24.;
+
+/// How the spectra are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visualization {
+    /// Current levels over frequency
+    #[default]
+    Spectrum,
+    /// Scrolling history of levels over frequency
+    Spectrogram,
+    /// Samples over time
+    Oscilloscope,
+    /// Left against right, with their phase correlation
+    Goniometer,
+}
+
+impl Visualization {
+    pub const ALL: [Visualization; 4] = [
+        Visualization::Spectrum,
+        Visualization::Spectrogram,
+        Visualization::Oscilloscope,
+        Visualization::Goniometer,
+    ];
+}
+
+impl fmt::Display for Visualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visualization::Spectrum => write!(f, "Spectrum"),
+            Visualization::Spectrogram => write!(f, "Spectrogram"),
+            Visualization::Oscilloscope => write!(f, "Oscilloscope"),
+            Visualization::Goniometer => write!(f, "Goniometer"),
+        }
+    }
+}
+
+/// Which spectra of the played audio are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrumView {
+    /// Left and right channel overlaid
+    #[default]
+    LeftRight,
+    Left,
+    Right,
+    Mid,
+    Side,
+}
+
+impl SpectrumView {
+    pub const ALL: [SpectrumView; 5] = [
+        SpectrumView::LeftRight,
+        SpectrumView::Left,
+        SpectrumView::Right,
+        SpectrumView::Mid,
+        SpectrumView::Side,
+    ];
+}
+
+impl fmt::Display for SpectrumView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectrumView::LeftRight => write!(f, "Left + Right"),
+            SpectrumView::Left => write!(f, "Left"),
+            SpectrumView::Right => write!(f, "Right"),
+            SpectrumView::Mid => write!(f, "Mid"),
+            SpectrumView::Side => write!(f, "Side"),
+        }
+    }
+}
+
+/// Traces drawn on top of the level of each spectrum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpectrumOverlays {
+    pub peak_hold: bool,
+    pub average: bool,
+}
+
+impl Default for SpectrumOverlays {
+    fn default() -> Self {
+        Self {
+            peak_hold: true,
+            average: false,
+        }
+    }
+}
+
 pub struct Scene {
-    spectrometer: Spectrometer,
-    spectrum: Vec<f32>,
+    visualization: Visualization,
+    view: SpectrumView,
+    scale: SpectrumScale,
+    ballistics: Ballistics,
+    overlays: SpectrumOverlays,
+    /// One per drawn spectrum, since each keeps its own ballistics
+    spectrometers: [Spectrometer; 2],
+    spectra: Vec<Traces>,
+    /// Levels in dBFS of the FFT bins of each drawn spectrum, as measured
+    bins: Vec<Vec<f32>>,
+    /// Frequency width of a bin in Hz
+    bin_size: f32,
+    spectrogram: Spectrogram,
+    oscilloscope: Oscilloscope,
+    goniometer: Goniometer,
     cache: Cache,
+    grid_cache: Cache,
 }
 
 impl Scene {
     pub fn new() -> Self {
-        let scene = Self {
-            spectrometer: Spectrometer::new(RESOLUTION),
-            spectrum: vec![0f32; RESOLUTION],
+        Self {
+            visualization: Visualization::default(),
+            view: SpectrumView::default(),
+            scale: SpectrumScale::default(),
+            ballistics: Ballistics::default(),
+            overlays: SpectrumOverlays::default(),
+            spectrometers: [
+                Spectrometer::new(RESOLUTION),
+                Spectrometer::new(RESOLUTION),
+            ],
+            spectra: vec![],
+            bins: vec![],
+            bin_size: 0.,
+            spectrogram: Spectrogram::new(SPECTROGRAM_ROWS),
+            oscilloscope: Oscilloscope::new(),
+            goniometer: Goniometer::new(),
             cache: Cache::default(),
-        };
+            grid_cache: Cache::default(),
+        }
+    }
+
+    pub fn get_visualization(&self) -> Visualization {
+        self.visualization
+    }
+
+    pub fn set_visualization(&mut self, visualization: Visualization) {
+        self.visualization = visualization;
+    }
+
+    pub fn get_view(&self) -> SpectrumView {
+        self.view
+    }
+
+    pub fn set_view(&mut self, view: SpectrumView) {
+        self.view = view;
+    }
+
+    pub fn get_scale(&self) -> SpectrumScale {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: SpectrumScale) {
+        self.scale = scale;
+        self.grid_cache.clear();
+        for spectrometer in &mut self.spectrometers {
+            spectrometer.set_scale(scale);
+        }
+    }
+
+    pub fn get_ballistics(&self) -> Ballistics {
+        self.ballistics
+    }
+
+    pub fn set_ballistics(&mut self, ballistics: Ballistics) {
+        self.ballistics = ballistics;
+        for spectrometer in &mut self.spectrometers {
+            spectrometer.set_ballistics(ballistics);
+        }
+    }
+
+    pub fn get_overlays(&self) -> SpectrumOverlays {
+        self.overlays
+    }
+
+    pub fn set_overlays(&mut self, overlays: SpectrumOverlays) {
+        self.overlays = overlays;
+        self.cache.clear();
+    }
+
+    pub fn get_spectrogram_config(&self) -> SpectrogramConfig {
+        self.spectrogram.get_config()
+    }
+
+    pub fn set_spectrogram_config(&mut self, config: SpectrogramConfig) {
+        self.spectrogram.set_config(config);
+    }
 
-        scene
+    pub fn get_scope_config(&self) -> ScopeConfig {
+        self.oscilloscope.get_config()
+    }
+
+    pub fn set_scope_config(&mut self, config: ScopeConfig) {
+        self.oscilloscope.set_config(config);
+    }
+
+    pub fn update_waveform(&mut self, waveform: &Waveform, dt: Duration) {
+        match self.visualization {
+            Visualization::Oscilloscope => self.oscilloscope.update(waveform),
+            Visualization::Goniometer => self.goniometer.update(waveform, dt),
+            Visualization::Spectrum | Visualization::Spectrogram => return,
+        }
+        self.cache.clear();
     }
 
     pub fn update_spectrum(
@@ -35,15 +238,114 @@ impl Scene {
         fft_spectrum: &FftSpectrum,
         dt: Duration,
     ) {
+        let magnitudes: &[&[f32]] = match self.view {
+            SpectrumView::LeftRight => {
+                &[fft_spectrum.left(), fft_spectrum.right()]
+            }
+            SpectrumView::Left => &[fft_spectrum.left()],
+            SpectrumView::Right => &[fft_spectrum.right()],
+            SpectrumView::Mid => &[&fft_spectrum.mid],
+            SpectrumView::Side => &[&fft_spectrum.side],
+        };
+
         self.cache.clear();
-        self.spectrum = self.spectrometer.generate_spectrum(fft_spectrum, dt);
+        self.bins.resize_with(magnitudes.len(), Vec::new);
+        for (bins, magnitudes) in self.bins.iter_mut().zip(magnitudes) {
+            bins.clear();
+            bins.extend_from_slice(magnitudes);
+        }
+        self.bin_size = fft_spectrum.bin_size;
+        self.spectra = magnitudes
+            .iter()
+            .zip(&mut self.spectrometers)
+            .map(|(magnitudes, spectrometer)| {
+                spectrometer.generate_spectrum(
+                    magnitudes,
+                    fft_spectrum.bin_size,
+                    dt,
+                )
+            })
+            .collect();
+
+        if self.visualization == Visualization::Spectrogram {
+            // Overlaid channels share the spectrogram by their highest level
+            let mut levels = vec![0f32; RESOLUTION];
+            for traces in &self.spectra {
+                for (level, value) in levels.iter_mut().zip(&traces.level) {
+                    *level = level.max(*value);
+                }
+            }
+            self.spectrogram.push(&levels, dt);
+        }
+    }
+
+    /// Levels in dBFS of the FFT bins of the drawn spectra at the frequency
+    /// of a horizontal position between 0 and 1, labeled by channel when
+    /// overlaid. They are read before the ballistics and the level range of
+    /// the scale, so they show what was measured.
+    fn get_levels_at(&self, position: f32) -> Vec<(&'static str, f32)> {
+        let names: &[&'static str] = match self.view {
+            SpectrumView::LeftRight => &["L ", "R "],
+            _ => &[""],
+        };
+        if self.bin_size <= 0. {
+            return vec![];
+        }
+
+        let frequency = self.scale.position_frequency(position);
+        self.bins
+            .iter()
+            .zip(names)
+            .filter(|(bins, _)| !bins.is_empty())
+            .map(|(bins, name)| {
+                let i = ((frequency / self.bin_size).round() as usize)
+                    .min(bins.len() - 1);
+                (*name, bins[i])
+            })
+            .collect()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        match self.visualization {
+            Visualization::Spectrum
+            | Visualization::Oscilloscope
+            | Visualization::Goniometer => Canvas::new(self)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            Visualization::Spectrogram => self.spectrogram_view(),
+        }
     }
 
-    pub fn view(&self) -> Element<Message> {
-        Canvas::new(self)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into()
+    fn spectrogram_view(&self) -> Element<'_, Message> {
+        let spectrogram = self.spectrogram.view();
+
+        match self.spectrogram.get_config().direction {
+            ScrollDirection::Horizontal => {
+                let axis = Canvas::new(FrequencyAxis {
+                    scale: self.scale,
+                    vertical: true,
+                })
+                .width(AXIS_WIDTH)
+                .height(Length::Fill);
+                row![axis, spectrogram].into()
+            }
+            ScrollDirection::Vertical => {
+                let axis = Canvas::new(FrequencyAxis {
+                    scale: self.scale,
+                    vertical: false,
+                })
+                .width(Length::Fill)
+                .height(AXIS_HEIGHT);
+                column![spectrogram, axis].into()
+            }
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -56,44 +358,137 @@ impl<Message> canvas::Program<Message> for Scene {
         renderer: &Renderer,
         theme: &Theme,
         bounds: Rectangle,
-        _cursor: Cursor,
+        cursor: Cursor,
     ) -> Vec<Geometry> {
+        match self.visualization {
+            Visualization::Oscilloscope => {
+                return vec![self.cache.draw(
+                    renderer,
+                    bounds.size(),
+                    |frame| {
+                        self.oscilloscope.draw(frame, theme);
+                    },
+                )];
+            }
+            Visualization::Goniometer => {
+                return vec![self.cache.draw(
+                    renderer,
+                    bounds.size(),
+                    |frame| {
+                        self.goniometer.draw(frame, theme);
+                    },
+                )];
+            }
+            Visualization::Spectrum | Visualization::Spectrogram => {}
+        }
+
+        let grid = self.grid_cache.draw(renderer, bounds.size(), |frame| {
+            axes::draw_grid(frame, &self.scale, theme);
+        });
+
         let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
             let frame_size = frame.size();
-            let points = get_points_for_spectrum(&self.spectrum, frame_size);
+            // The second spectrum is the right channel when overlaid
+            let colors = [theme.palette().text, theme.palette().primary];
+
+            for (traces, color) in self.spectra.iter().zip(colors) {
+                let points = get_points_for_spectrum(&traces.level, frame_size);
+
+                let path = Path::new(|b| {
+                    b.move_to(Point::new(0f32, frame_size.height));
+
+                    for point in points {
+                        b.line_to(point);
+                    }
+
+                    b.line_to(Point::new(frame_size.width, frame_size.height));
+                });
 
-            let path = Path::new(|b| {
-                b.move_to(Point::new(0f32, frame_size.height));
+                frame.fill(&path, Color { a: 0.15, ..color });
+                frame.stroke(
+                    &path,
+                    Stroke {
+                        style: stroke::Style::Solid(color),
+                        width: 1.0,
+                        ..Stroke::default()
+                    },
+                );
 
-                for point in points {
-                    b.line_to(point);
+                if self.overlays.peak_hold {
+                    frame.stroke(
+                        &get_line_path(&traces.peak, frame_size),
+                        Stroke::default()
+                            .with_color(Color { a: 0.6, ..color })
+                            .with_width(1.),
+                    );
                 }
+                if self.overlays.average {
+                    frame.stroke(
+                        &get_line_path(&traces.average, frame_size),
+                        Stroke {
+                            line_dash: LineDash {
+                                segments: &[6., 4.],
+                                offset: 0,
+                            },
+                            ..Stroke::default().with_color(color).with_width(2.)
+                        },
+                    );
+                }
+            }
+        });
 
-                b.line_to(Point::new(frame_size.width, frame_size.height));
-            });
-
-            frame.fill(&path, Color::from_rgba8(108, 122, 137, 0.3));
-            frame.stroke(
-                &path,
-                Stroke {
-                    style: stroke::Style::Solid(theme.palette().text),
-                    width: 1.0,
-                    ..Stroke::default()
-                },
+        let mut geometries = vec![grid, geometry];
+        if let Some(position) = cursor.position_in(bounds) {
+            let mut frame = Frame::new(renderer, bounds.size());
+            let levels = self.get_levels_at(position.x / bounds.width);
+            axes::draw_crosshair(
+                &mut frame,
+                &self.scale,
+                position,
+                &levels,
+                theme,
             );
-        });
+            geometries.push(frame.into_geometry());
+        }
+
+        geometries
+    }
 
-        vec![geometry]
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> mouse::Interaction {
+        if self.visualization == Visualization::Spectrum
+            && cursor.is_over(bounds)
+        {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        }
     }
 }
 
+fn get_line_path(data: &[f32], frame_size: Size) -> Path {
+    Path::new(|b| {
+        let mut points = get_points_for_spectrum(data, frame_size).into_iter();
+        if let Some(first) = points.next() {
+            b.move_to(first);
+        }
+        for point in points {
+            b.line_to(point);
+        }
+    })
+}
+
 fn get_points_for_spectrum(data: &[f32], frame_size: Size) -> Vec<Point> {
     let mut points: Vec<Point> = vec![];
     let step_size = frame_size.width / data.len() as f32;
 
     for (i, vertex) in data.iter().enumerate() {
         let x = i as f32 * step_size;
-        let y = frame_size.height - vertex * frame_size.height * 0.7;
+        let y = frame_size.height * (1. - vertex);
 
         points.push(Point::new(x, y));
     }
@@ -0,0 +1,243 @@
+use super::SpectrumScale;
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse::Cursor;
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Theme};
+
+pub const LABEL_SIZE: f32 = 12.;
+pub const LABEL_PADDING: f32 = 4.;
+
+/// Gridline frequencies, 1-2-5 steps per decade
+const FREQUENCIES: [f32; 13] = [
+    10., 20., 50., 100., 200., 500., 1000., 2000., 5000., 10000., 20000.,
+    50000., 100000.,
+];
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Draws labeled frequency and level gridlines
+pub fn draw_grid(frame: &mut Frame, scale: &SpectrumScale, theme: &Theme) {
+    let size = frame.size();
+    let line_color = Color {
+        a: 0.15,
+        ..theme.palette().text
+    };
+    let label_color = Color {
+        a: 0.6,
+        ..theme.palette().text
+    };
+    let line = Stroke::default().with_color(line_color).with_width(1.);
+
+    for frequency in FREQUENCIES {
+        if frequency < scale.min_frequency || frequency > scale.max_frequency {
+            continue;
+        }
+
+        let x = (scale.frequency_position(frequency) * size.width).round();
+        frame.stroke(
+            &Path::line(Point::new(x, 0.), Point::new(x, size.height)),
+            line.clone(),
+        );
+        frame.fill_text(Text {
+            content: format_frequency(frequency),
+            position: Point::new(
+                x + LABEL_PADDING,
+                size.height - LABEL_PADDING,
+            ),
+            color: label_color,
+            size: LABEL_SIZE.into(),
+            vertical_alignment: Vertical::Bottom,
+            ..Text::default()
+        });
+    }
+
+    // Aim for about one line per 40 pixels, in steps of 6, 10 or 20 dB
+    let range = scale.ceiling - scale.floor;
+    let step = [6., 10., 20.]
+        .into_iter()
+        .find(|step| size.height * step / range >= 40.)
+        .unwrap_or(20.);
+    let mut level = (scale.ceiling / step).floor() * step;
+    while level > scale.floor {
+        let y = ((1. - scale.level_position(level)) * size.height).round();
+        frame.stroke(
+            &Path::line(Point::new(0., y), Point::new(size.width, y)),
+            line.clone(),
+        );
+        frame.fill_text(Text {
+            content: format!("{} dB", level),
+            position: Point::new(LABEL_PADDING, y + LABEL_PADDING),
+            color: label_color,
+            size: LABEL_SIZE.into(),
+            ..Text::default()
+        });
+        level -= step;
+    }
+}
+
+/// Draws a crosshair at `position` with the frequency, the nearest note and
+/// the level of each spectrum at that frequency
+pub fn draw_crosshair(
+    frame: &mut Frame,
+    scale: &SpectrumScale,
+    position: Point,
+    levels: &[(&str, f32)],
+    theme: &Theme,
+) {
+    let size = frame.size();
+    let color = theme.palette().text;
+    let line = Stroke::default()
+        .with_color(Color { a: 0.5, ..color })
+        .with_width(1.);
+
+    frame.stroke(
+        &Path::line(
+            Point::new(position.x, 0.),
+            Point::new(position.x, size.height),
+        ),
+        line.clone(),
+    );
+    frame.stroke(
+        &Path::line(
+            Point::new(0., position.y),
+            Point::new(size.width, position.y),
+        ),
+        line,
+    );
+
+    let frequency = scale.position_frequency(position.x / size.width);
+    let mut content =
+        format!("{}  {}", format_frequency(frequency), note_name(frequency));
+    for (name, level) in levels {
+        content.push_str(&format!("  {}{:.1} dB", name, level));
+    }
+
+    // Keep the readout inside the frame
+    let (x, horizontal_alignment) = if position.x > size.width / 2. {
+        (position.x - LABEL_PADDING, Horizontal::Right)
+    } else {
+        (position.x + LABEL_PADDING, Horizontal::Left)
+    };
+    let (y, vertical_alignment) = if position.y > LABEL_SIZE * 3. {
+        (position.y - LABEL_PADDING, Vertical::Bottom)
+    } else {
+        (position.y + LABEL_PADDING, Vertical::Top)
+    };
+
+    frame.fill_text(Text {
+        content,
+        position: Point::new(x, y),
+        color,
+        size: LABEL_SIZE.into(),
+        horizontal_alignment,
+        vertical_alignment,
+        ..Text::default()
+    });
+}
+
+/// Frequency labels beside a view without its own axes, like the
+/// spectrogram
+#[derive(Debug, Clone, Copy)]
+pub struct FrequencyAxis {
+    pub scale: SpectrumScale,
+    /// Frequency goes up instead of to the right
+    pub vertical: bool,
+}
+
+impl<Message> canvas::Program<Message> for FrequencyAxis {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let size = frame.size();
+        let color = Color {
+            a: 0.6,
+            ..theme.palette().text
+        };
+        let tick = Stroke::default().with_color(color).with_width(1.);
+
+        for frequency in FREQUENCIES {
+            if frequency < self.scale.min_frequency
+                || frequency > self.scale.max_frequency
+            {
+                continue;
+            }
+
+            let position = self.scale.frequency_position(frequency);
+            let (from, to, text) = if self.vertical {
+                let y = ((1. - position) * size.height).round();
+                (
+                    Point::new(size.width - LABEL_PADDING, y),
+                    Point::new(size.width, y),
+                    Text {
+                        position: Point::new(
+                            size.width - LABEL_PADDING * 2.,
+                            y,
+                        ),
+                        horizontal_alignment: Horizontal::Right,
+                        vertical_alignment: Vertical::Center,
+                        ..Text::default()
+                    },
+                )
+            } else {
+                let x = (position * size.width).round();
+                (
+                    Point::new(x, 0.),
+                    Point::new(x, LABEL_PADDING),
+                    Text {
+                        position: Point::new(x, LABEL_PADDING * 2.),
+                        horizontal_alignment: Horizontal::Center,
+                        ..Text::default()
+                    },
+                )
+            };
+
+            frame.stroke(&Path::line(from, to), tick.clone());
+            frame.fill_text(Text {
+                content: format_frequency(frequency),
+                color,
+                size: LABEL_SIZE.into(),
+                ..text
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000. {
+        format!("{:.3}", frequency / 1000.)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+            + " kHz"
+    } else {
+        format!("{:.0} Hz", frequency)
+    }
+}
+
+/// Name of the equal tempered note closest to `frequency`, with A4 at 440 Hz
+/// and the deviation in cents
+fn note_name(frequency: f32) -> String {
+    let midi = 69. + 12. * (frequency / 440.).log2();
+    let note = midi.round();
+    let cents = ((midi - note) * 100.).round() as i32;
+    let note = note as i32;
+
+    format!(
+        "{}{} {:+}c",
+        NOTE_NAMES[note.rem_euclid(12) as usize],
+        note.div_euclid(12) - 1,
+        cents
+    )
+}
@@ -0,0 +1,289 @@
+use super::axes::{LABEL_PADDING, LABEL_SIZE};
+use crate::player::Waveform;
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::canvas::{Frame, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Size, Theme};
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::time::Duration;
+
+/// Length of the most recent audio drawn as a Lissajous figure, in seconds
+const TRACE_DURATION: f32 = 0.05;
+
+/// Length of the audio the correlation is measured over, in seconds
+const CORRELATION_DURATION: f32 = 0.3;
+
+/// Time between the correlations kept in the history
+const HISTORY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Number of correlations kept, 10 seconds at `HISTORY_INTERVAL`
+const HISTORY_LEN: usize = 200;
+
+/// Height of the correlation meter bar
+const METER_HEIGHT: f32 = 24.;
+
+/// Stereo image of the most recent audio, with left against right rotated by
+/// 45 degrees so mono is vertical, and the phase correlation between them
+pub struct Goniometer {
+    /// Side and mid of each recent frame, between -1 and 1 at full scale
+    points: Vec<(f32, f32)>,
+    /// Correlation between -1 for opposite and 1 for identical channels
+    correlation: f32,
+    /// Correlations of the last seconds, oldest first
+    history: VecDeque<f32>,
+    elapsed: Duration,
+}
+
+impl Goniometer {
+    pub fn new() -> Self {
+        Self {
+            points: vec![],
+            correlation: 0.,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Takes the most recent frames of the first two channels, where mono
+    /// counts as both
+    pub fn update(&mut self, waveform: &Waveform, dt: Duration) {
+        let Some(left) = waveform.channels.first() else {
+            self.points.clear();
+            return;
+        };
+        let right = waveform.channels.get(1).unwrap_or(left);
+
+        let recent = |duration: f32| {
+            let frames = (duration * waveform.sample_rate as f32) as usize;
+            left.len() - frames.min(left.len())
+        };
+
+        let start = recent(TRACE_DURATION);
+        self.points.clear();
+        self.points
+            .extend(left[start..].iter().zip(&right[start..]).map(
+                |(left, right)| {
+                    (
+                        (right - left) * FRAC_1_SQRT_2,
+                        (left + right) * FRAC_1_SQRT_2,
+                    )
+                },
+            ));
+
+        let start = recent(CORRELATION_DURATION);
+        let (mut product, mut left_energy, mut right_energy) = (0., 0., 0.);
+        for (left, right) in left[start..].iter().zip(&right[start..]) {
+            product += left * right;
+            left_energy += left * left;
+            right_energy += right * right;
+        }
+        let energy = (left_energy * right_energy).sqrt();
+        // Silence has no phase to speak of
+        self.correlation = if energy > f32::EPSILON {
+            (product / energy).clamp(-1., 1.)
+        } else {
+            0.
+        };
+
+        self.elapsed += dt;
+        while self.elapsed >= HISTORY_INTERVAL {
+            self.elapsed -= HISTORY_INTERVAL;
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.correlation);
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        let size = frame.size();
+        let side = size.height.min(size.width / 2.);
+        self.draw_scope(frame, theme, side);
+
+        let x = side + LABEL_PADDING * 4.;
+        let width = size.width - x;
+        self.draw_meter(
+            frame,
+            theme,
+            Rectangle::new(Point::new(x, 0.), Size::new(width, METER_HEIGHT)),
+        );
+        self.draw_history(
+            frame,
+            theme,
+            Rectangle::new(
+                Point::new(x, METER_HEIGHT + LABEL_PADDING * 4.),
+                Size::new(
+                    width,
+                    size.height - METER_HEIGHT - LABEL_PADDING * 4.,
+                ),
+            ),
+        );
+    }
+
+    /// Draws the Lissajous figure in a square of `side` at the top left
+    fn draw_scope(&self, frame: &mut Frame, theme: &Theme, side: f32) {
+        let palette = theme.palette();
+        let line = Stroke::default()
+            .with_color(Color {
+                a: 0.15,
+                ..palette.text
+            })
+            .with_width(1.);
+        let label_color = Color {
+            a: 0.6,
+            ..palette.text
+        };
+
+        let radius = side / 2.;
+        let center = Point::new(radius, radius);
+        let to_point = |(x, y): (f32, f32)| {
+            Point::new(center.x + x * radius, center.y - y * radius)
+        };
+
+        // Left and right on the diagonals, mid and side on the axes
+        let axes = [
+            ((-FRAC_1_SQRT_2, FRAC_1_SQRT_2), "L"),
+            ((FRAC_1_SQRT_2, FRAC_1_SQRT_2), "R"),
+            ((0., 1.), "M"),
+            ((1., 0.), "+S"),
+            ((-1., 0.), "-S"),
+        ];
+        for ((x, y), label) in axes {
+            frame.stroke(
+                &Path::line(to_point((-x, -y)), to_point((x, y))),
+                line.clone(),
+            );
+            let position = to_point((x * 0.9, y * 0.9));
+            frame.fill_text(Text {
+                content: label.to_string(),
+                position,
+                color: label_color,
+                size: LABEL_SIZE.into(),
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                ..Text::default()
+            });
+        }
+
+        let path = Path::new(|b| {
+            for (i, point) in self.points.iter().enumerate() {
+                let point = to_point(*point);
+                if i == 0 {
+                    b.move_to(point);
+                } else {
+                    b.line_to(point);
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default()
+                .with_color(Color {
+                    a: 0.6,
+                    ..palette.primary
+                })
+                .with_width(1.),
+        );
+    }
+
+    /// Draws the current correlation as a bar from the center of `bounds`
+    fn draw_meter(&self, frame: &mut Frame, theme: &Theme, bounds: Rectangle) {
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            bounds.position(),
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+
+        let center = bounds.x + bounds.width / 2.;
+        let end = center + self.correlation * bounds.width / 2.;
+        // Negative correlation means trouble when summed to mono
+        let color = if self.correlation < 0. {
+            palette.danger
+        } else {
+            palette.success
+        };
+        frame.fill_rectangle(
+            Point::new(center.min(end), bounds.y),
+            Size::new((end - center).abs(), bounds.height),
+            color,
+        );
+
+        for (position, label) in [(0., "-1"), (0.5, "0"), (1., "+1")] {
+            let x = bounds.x + position * bounds.width;
+            let (x, horizontal_alignment) = match label {
+                "-1" => (x + LABEL_PADDING, Horizontal::Left),
+                "+1" => (x - LABEL_PADDING, Horizontal::Right),
+                _ => (x, Horizontal::Center),
+            };
+            frame.fill_text(Text {
+                content: label.to_string(),
+                position: Point::new(x, bounds.center_y()),
+                color: palette.text,
+                size: LABEL_SIZE.into(),
+                horizontal_alignment,
+                vertical_alignment: Vertical::Center,
+                ..Text::default()
+            });
+        }
+    }
+
+    /// Draws the correlation history over time, newest on the right
+    fn draw_history(
+        &self,
+        frame: &mut Frame,
+        theme: &Theme,
+        bounds: Rectangle,
+    ) {
+        let palette = theme.palette();
+        let line = Stroke::default()
+            .with_color(Color {
+                a: 0.15,
+                ..palette.text
+            })
+            .with_width(1.);
+        let to_y = |correlation: f32| {
+            bounds.y + (1. - correlation) / 2. * bounds.height
+        };
+
+        for correlation in [-1., 0., 1.] {
+            let y = to_y(correlation);
+            frame.stroke(
+                &Path::line(
+                    Point::new(bounds.x, y),
+                    Point::new(bounds.x + bounds.width, y),
+                ),
+                line.clone(),
+            );
+        }
+
+        let step = bounds.width / (HISTORY_LEN - 1) as f32;
+        let offset = HISTORY_LEN - self.history.len();
+        let path = Path::new(|b| {
+            for (i, correlation) in self.history.iter().enumerate() {
+                let point = Point::new(
+                    bounds.x + (offset + i) as f32 * step,
+                    to_y(*correlation),
+                );
+                if i == 0 {
+                    b.move_to(point);
+                } else {
+                    b.line_to(point);
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default().with_color(palette.text).with_width(1.),
+        );
+    }
+}
+
+impl Default for Goniometer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,151 @@
+use super::axes::{LABEL_PADDING, LABEL_SIZE};
+use crate::player::Waveform;
+use iced::alignment::Vertical;
+use iced::widget::canvas::{Frame, Path, Stroke, Text};
+use iced::{Color, Point, Theme};
+
+/// Number of divisions of the time window
+const DIVISIONS: usize = 10;
+
+/// How the oscilloscope shows the waveform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScopeConfig {
+    /// Time shown across the width in milliseconds
+    pub window: f32,
+    /// Amplification of the samples, where 1 puts full scale at the edges
+    pub gain: f32,
+}
+
+impl Default for ScopeConfig {
+    fn default() -> Self {
+        Self {
+            window: 20.,
+            gain: 1.,
+        }
+    }
+}
+
+/// Trigger-stabilized view of the most recent samples, so periodic signals
+/// stand still
+pub struct Oscilloscope {
+    config: ScopeConfig,
+    /// Samples of each channel shown across the width
+    traces: Vec<Vec<f32>>,
+}
+
+impl Oscilloscope {
+    pub fn new() -> Self {
+        Self {
+            config: ScopeConfig::default(),
+            traces: vec![],
+        }
+    }
+
+    pub fn get_config(&self) -> ScopeConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: ScopeConfig) {
+        self.config = config;
+    }
+
+    /// Takes the window of frames that starts at the latest rising zero
+    /// crossing of the first channel, or the latest frames when there is none
+    pub fn update(&mut self, waveform: &Waveform) {
+        let Some(first) = waveform.channels.first() else {
+            self.traces.clear();
+            return;
+        };
+
+        let len = first.len();
+        let window =
+            ((self.config.window / 1000. * waveform.sample_rate as f32)
+                as usize)
+                .clamp(2, len.max(2));
+        if len < window {
+            self.traces.clear();
+            return;
+        }
+
+        // Leave room for about one window of history to find a trigger in
+        let latest = len - window;
+        let earliest = latest.saturating_sub(window).max(1);
+        let start = (earliest..=latest)
+            .rev()
+            .find(|&i| first[i - 1] < 0. && first[i] >= 0.)
+            .unwrap_or(latest);
+
+        self.traces.resize(waveform.channels.len(), vec![]);
+        for (trace, channel) in self.traces.iter_mut().zip(&waveform.channels) {
+            trace.clear();
+            trace.extend_from_slice(&channel[start..start + window]);
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        let size = frame.size();
+        let palette = theme.palette();
+        let line = Stroke::default()
+            .with_color(Color {
+                a: 0.15,
+                ..palette.text
+            })
+            .with_width(1.);
+        let label_color = Color {
+            a: 0.6,
+            ..palette.text
+        };
+
+        let center = size.height / 2.;
+        frame.stroke(
+            &Path::line(Point::new(0., center), Point::new(size.width, center)),
+            line.clone(),
+        );
+        for division in 1..DIVISIONS {
+            let x = (division as f32 / DIVISIONS as f32 * size.width).round();
+            frame.stroke(
+                &Path::line(Point::new(x, 0.), Point::new(x, size.height)),
+                line.clone(),
+            );
+        }
+        frame.fill_text(Text {
+            content: format!(
+                "{} ms/div  x{}",
+                self.config.window / DIVISIONS as f32,
+                self.config.gain
+            ),
+            position: Point::new(LABEL_PADDING, size.height - LABEL_PADDING),
+            color: label_color,
+            size: LABEL_SIZE.into(),
+            vertical_alignment: Vertical::Bottom,
+            ..Text::default()
+        });
+
+        // The second channel is the right one
+        let colors = [palette.text, palette.primary];
+        for (trace, color) in self.traces.iter().zip(colors) {
+            let step = size.width / (trace.len() - 1) as f32;
+            let path = Path::new(|b| {
+                for (i, sample) in trace.iter().enumerate() {
+                    let y = center * (1. - sample * self.config.gain);
+                    let point = Point::new(i as f32 * step, y);
+                    if i == 0 {
+                        b.move_to(point);
+                    } else {
+                        b.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(
+                &path,
+                Stroke::default().with_color(color).with_width(1.),
+            );
+        }
+    }
+}
+
+impl Default for Oscilloscope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
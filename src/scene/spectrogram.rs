@@ -0,0 +1,251 @@
+use iced::widget::image::{self, FilterMethod};
+use iced::widget::{Column, Image, Row};
+use iced::{ContentFit, Element, Length};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+/// Number of time steps kept, whatever the span
+const STEPS: usize = 600;
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [72, 40, 120],
+    [62, 73, 137],
+    [49, 104, 142],
+    [38, 130, 142],
+    [31, 158, 137],
+    [53, 183, 121],
+    [110, 206, 88],
+    [253, 231, 37],
+];
+
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [28, 16, 68],
+    [79, 18, 123],
+    [129, 37, 129],
+    [181, 54, 122],
+    [229, 80, 100],
+    [251, 135, 97],
+    [254, 194, 135],
+    [252, 253, 191],
+];
+
+const GRAYSCALE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+
+/// Maps display values between 0 and 1 to colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 3] =
+        [Colormap::Viridis, Colormap::Magma, Colormap::Grayscale];
+
+    /// RGBA color of `value`, linearly interpolated between the stops
+    fn color(&self, value: f32) -> [u8; 4] {
+        let stops: &[[u8; 3]] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Grayscale => &GRAYSCALE,
+        };
+
+        let position = value.clamp(0., 1.) * (stops.len() - 1) as f32;
+        let i = (position as usize).min(stops.len() - 2);
+        let t = position - i as f32;
+        let channel = |c: usize| {
+            let from = stops[i][c] as f32;
+            let to = stops[i + 1][c] as f32;
+            (from + (to - from) * t).round() as u8
+        };
+
+        [channel(0), channel(1), channel(2), 255]
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Colormap::Viridis => write!(f, "Viridis"),
+            Colormap::Magma => write!(f, "Magma"),
+            Colormap::Grayscale => write!(f, "Grayscale"),
+        }
+    }
+}
+
+/// Which way time moves through the spectrogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollDirection {
+    /// Newest on the right, with frequency going up
+    #[default]
+    Horizontal,
+    /// Newest at the top, with frequency going right
+    Vertical,
+}
+
+impl ScrollDirection {
+    pub const ALL: [ScrollDirection; 2] =
+        [ScrollDirection::Horizontal, ScrollDirection::Vertical];
+}
+
+impl fmt::Display for ScrollDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrollDirection::Horizontal => write!(f, "Horizontal"),
+            ScrollDirection::Vertical => write!(f, "Vertical"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpectrogramConfig {
+    pub colormap: Colormap,
+    pub direction: ScrollDirection,
+    /// Seconds of history shown
+    pub span: u32,
+}
+
+impl SpectrogramConfig {
+    pub const SPANS: [u32; 5] = [5, 10, 20, 30, 60];
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            colormap: Colormap::default(),
+            direction: ScrollDirection::default(),
+            span: 10,
+        }
+    }
+}
+
+/// History of spectra as a strip of images, one line of pixels per time
+/// step. Only the line of a new time step is colored and uploaded, the older
+/// ones keep the images they already have.
+pub struct Spectrogram {
+    config: SpectrogramConfig,
+    /// Number of frequency pixels
+    rows: usize,
+    /// Line of every time step, oldest first
+    lines: VecDeque<image::Handle>,
+    /// Line of the time steps before anything was played
+    blank: image::Handle,
+    /// Highest values since the last time step
+    pending: Vec<f32>,
+    elapsed: Duration,
+}
+
+impl Spectrogram {
+    pub fn new(rows: usize) -> Self {
+        let blank = image::Handle::from_pixels(1, 1, vec![0u8; 4]);
+        Self {
+            config: SpectrogramConfig::default(),
+            rows,
+            lines: VecDeque::from(vec![blank.clone(); STEPS]),
+            blank,
+            pending: vec![0f32; rows],
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn get_config(&self) -> SpectrogramConfig {
+        self.config
+    }
+
+    /// Changing the colors or the direction starts a new history
+    pub fn set_config(&mut self, config: SpectrogramConfig) {
+        let clear = config.colormap != self.config.colormap
+            || config.direction != self.config.direction;
+        self.config = config;
+        if clear {
+            self.lines
+                .iter_mut()
+                .for_each(|line| *line = self.blank.clone());
+        }
+    }
+
+    /// Adds display values between 0 and 1 spread over the frequency axis,
+    /// which are combined into time steps of `span / STEPS`
+    pub fn push(&mut self, values: &[f32], dt: Duration) {
+        if !values.is_empty() {
+            for (row, pending) in self.pending.iter_mut().enumerate() {
+                let start = row * values.len() / self.rows;
+                let end = ((row + 1) * values.len() / self.rows).max(start + 1);
+                for value in &values[start..end.min(values.len())] {
+                    *pending = pending.max(*value);
+                }
+            }
+        }
+
+        let step = Duration::from_secs(self.config.span as u64) / STEPS as u32;
+        self.elapsed += dt;
+        let steps = (self.elapsed.as_secs_f64() / step.as_secs_f64()) as usize;
+        if steps == 0 {
+            return;
+        }
+        self.elapsed -= step * steps as u32;
+
+        // Steps missed in between repeat the same line
+        let line = self.line();
+        for _ in 0..steps.min(STEPS) {
+            self.lines.pop_front();
+            self.lines.push_back(line.clone());
+        }
+        self.pending.fill(0.);
+    }
+
+    /// Image of the pending values, a column with high frequencies at the top
+    /// or a row with high frequencies on the right
+    fn line(&self) -> image::Handle {
+        let mut pixels = vec![0u8; self.rows * 4];
+        for (row, value) in self.pending.iter().enumerate() {
+            let index = match self.config.direction {
+                ScrollDirection::Horizontal => self.rows - 1 - row,
+                ScrollDirection::Vertical => row,
+            };
+            pixels[index * 4..index * 4 + 4]
+                .copy_from_slice(&self.config.colormap.color(*value));
+        }
+
+        match self.config.direction {
+            ScrollDirection::Horizontal => {
+                image::Handle::from_pixels(1, self.rows as u32, pixels)
+            }
+            ScrollDirection::Vertical => {
+                image::Handle::from_pixels(self.rows as u32, 1, pixels)
+            }
+        }
+    }
+
+    /// The lines side by side, oldest on the left or newest at the top
+    pub fn view<'a, Message: 'a>(&self) -> Element<'a, Message> {
+        let line = |handle: &image::Handle| {
+            Image::new(handle.clone())
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .content_fit(ContentFit::Fill)
+                .filter_method(FilterMethod::Nearest)
+                .into()
+        };
+
+        match self.config.direction {
+            ScrollDirection::Horizontal => {
+                Row::with_children(self.lines.iter().map(line))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into()
+            }
+            ScrollDirection::Vertical => {
+                Column::with_children(self.lines.iter().rev().map(line))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into()
+            }
+        }
+    }
+}
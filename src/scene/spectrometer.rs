@@ -1,7 +1,89 @@
-use crate::player::{FftSpectrum, BUFFER_SIZE};
+use crate::player::MIN_DBFS;
 use std::time::Duration;
 
-const SMOOTHING_SPEED: f32 = 5.;
+/// Ranges of the spectrum display axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumScale {
+    /// Level in dBFS drawn at the bottom
+    pub floor: f32,
+    /// Level in dBFS drawn at the top
+    pub ceiling: f32,
+    /// Frequency in Hz at the left edge
+    pub min_frequency: f32,
+    /// Frequency in Hz at the right edge
+    pub max_frequency: f32,
+}
+
+impl SpectrumScale {
+    /// Horizontal position between 0 and 1 of a frequency on a logarithmic
+    /// axis
+    pub fn frequency_position(&self, frequency: f32) -> f32 {
+        (frequency / self.min_frequency).ln()
+            / (self.max_frequency / self.min_frequency).ln()
+    }
+
+    /// Inverse of `frequency_position`
+    pub fn position_frequency(&self, position: f32) -> f32 {
+        self.min_frequency
+            * (self.max_frequency / self.min_frequency).powf(position)
+    }
+
+    /// Vertical position between 0 and 1 of a level in dBFS
+    pub fn level_position(&self, level: f32) -> f32 {
+        ((level - self.floor) / (self.ceiling - self.floor)).clamp(0., 1.)
+    }
+
+    /// Inverse of `level_position`
+    pub fn position_level(&self, position: f32) -> f32 {
+        self.floor + position * (self.ceiling - self.floor)
+    }
+}
+
+impl Default for SpectrumScale {
+    fn default() -> Self {
+        Self {
+            floor: -100.,
+            ceiling: 0.,
+            min_frequency: 20.,
+            max_frequency: 20000.,
+        }
+    }
+}
+
+/// How the drawn traces follow the analysed levels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ballistics {
+    /// Time constant of the level trace when it rises
+    pub attack: Duration,
+    /// Time constant of the level trace when it falls
+    pub release: Duration,
+    /// How long a peak is held before it starts to decay
+    pub hold: Duration,
+    /// Rate in dB per second at which a held peak falls
+    pub decay: f32,
+    /// Time constant of the long-term average, which averages power
+    pub average: Duration,
+}
+
+impl Default for Ballistics {
+    fn default() -> Self {
+        Self {
+            attack: Duration::from_millis(50),
+            release: Duration::from_millis(300),
+            hold: Duration::from_secs(1),
+            decay: 20.,
+            average: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Display values between 0 and 1 of the traces of one spectrum
+#[derive(Debug, Clone, Default)]
+pub struct Traces {
+    pub level: Vec<f32>,
+    pub peak: Vec<f32>,
+    pub average: Vec<f32>,
+}
 
 #[derive(Clone, Debug)]
 struct FrequencyVertex {
@@ -11,8 +93,17 @@ struct FrequencyVertex {
 }
 
 pub struct Spectrometer {
-    smoothing_buffer: [f32; BUFFER_SIZE],
+    /// Level in dBFS of each visible bin after attack and release
+    levels: Vec<f32>,
+    /// Held peak in dBFS of each visible bin
+    peaks: Vec<f32>,
+    /// Time since each peak was last raised
+    peak_ages: Vec<Duration>,
+    /// Long-term average power of each visible bin
+    powers: Vec<f32>,
     resolution: usize,
+    scale: SpectrumScale,
+    ballistics: Ballistics,
 }
 
 impl FrequencyVertex {
@@ -28,72 +119,142 @@ impl FrequencyVertex {
 impl Spectrometer {
     pub fn new(resolution: usize) -> Self {
         Spectrometer {
-            smoothing_buffer: [0f32; BUFFER_SIZE],
+            levels: vec![],
+            peaks: vec![],
+            peak_ages: vec![],
+            powers: vec![],
             resolution,
+            scale: SpectrumScale::default(),
+            ballistics: Ballistics::default(),
         }
     }
 
+    pub fn set_scale(&mut self, scale: SpectrumScale) {
+        self.scale = scale;
+    }
+
+    pub fn set_ballistics(&mut self, ballistics: Ballistics) {
+        self.ballistics = ballistics;
+    }
+
+    /// Turns the dBFS levels of FFT bins that are `bin_size` Hz wide into
+    /// `resolution` display values between 0 and 1 for the level, peak-hold
+    /// and average traces, spread over the frequency range of the scale
     pub fn generate_spectrum(
         &mut self,
-        fft_spectrum: &FftSpectrum,
+        levels: &[f32],
+        bin_size: f32,
         dt: Duration,
-    ) -> Vec<f32> {
-        let mut vertices = fft_spectrum
-            .values
-            .iter()
-            .enumerate()
-            .map(|(i, magnitude)| FrequencyVertex {
-                position: i as f32,
-                volume: magnitude.clone(),
-                frequency: (i as f32 + 1.) * fft_spectrum.bin_size,
-            })
-            .collect::<Vec<FrequencyVertex>>();
+    ) -> Traces {
+        let mut vertices = self.get_visible_vertices(levels, bin_size);
 
         self.apply_logarithmic_scaling(&mut vertices);
-        self.apply_normalised_positioning(&mut vertices);
-        self.apply_smoothing(&mut vertices, dt);
-        vertices = self.get_interpolated_vertices(vertices);
+        self.apply_ballistics(&vertices, dt);
 
-        let spectrum = vertices.iter().map(|vertex| vertex.volume).collect();
+        let average = self
+            .powers
+            .iter()
+            .map(|power| (10. * power.log10()).max(MIN_DBFS))
+            .collect::<Vec<_>>();
+        let trace = |levels: &[f32]| {
+            let mut vertices = vertices.clone();
+            for (vertex, level) in vertices.iter_mut().zip(levels) {
+                vertex.volume = *level;
+            }
+            self.apply_level_scaling(&mut vertices);
+            self.get_interpolated_vertices(vertices)
+                .iter()
+                .map(|vertex| vertex.volume)
+                .collect()
+        };
+
+        Traces {
+            level: trace(&self.levels),
+            peak: trace(&self.peaks),
+            average: trace(&average),
+        }
+    }
+
+    /// Vertices of the bins within the frequency range, plus one on each
+    /// side so the curve reaches the edges
+    fn get_visible_vertices(
+        &self,
+        levels: &[f32],
+        bin_size: f32,
+    ) -> Vec<FrequencyVertex> {
+        if bin_size <= 0. {
+            return vec![];
+        }
+
+        // Skip DC, which has no place on a logarithmic axis
+        let first = ((self.scale.min_frequency / bin_size).floor() as usize)
+            .max(1)
+            .min(levels.len());
+        let last = ((self.scale.max_frequency / bin_size).ceil() as usize + 1)
+            .min(levels.len());
 
-        spectrum
+        (first..last)
+            .map(|i| FrequencyVertex {
+                position: 0.,
+                volume: levels[i],
+                frequency: i as f32 * bin_size,
+            })
+            .collect()
     }
 
-    fn apply_logarithmic_scaling(&self, vertices: &mut Vec<FrequencyVertex>) {
+    fn apply_logarithmic_scaling(&self, vertices: &mut [FrequencyVertex]) {
         for vertex in vertices {
-            vertex.position = vertex.position.sqrt();
-            vertex.volume = (vertex.volume + 1.0).log10();
+            vertex.position = self.scale.frequency_position(vertex.frequency);
         }
     }
 
-    fn apply_normalised_positioning(
-        &self,
-        vertices: &mut Vec<FrequencyVertex>,
-    ) {
-        let max_vol = 2.5;
-        let max_pos = match vertices.last() {
-            Some(vertex) => vertex.position,
-            None => 1f32,
-        };
+    fn apply_level_scaling(&self, vertices: &mut [FrequencyVertex]) {
         for vertex in vertices {
-            vertex.position /= max_pos;
-            vertex.volume /= max_vol;
+            vertex.volume = self.scale.level_position(vertex.volume);
         }
     }
 
-    fn apply_smoothing(
-        &mut self,
-        vertices: &mut Vec<FrequencyVertex>,
-        dt: Duration,
-    ) {
-        for (i, vertex) in vertices.iter_mut().enumerate() {
-            if !vertex.volume.is_nan() {
-                self.smoothing_buffer[i] += (vertex.volume
-                    - self.smoothing_buffer[i])
-                    * dt.as_secs_f32()
-                    * SMOOTHING_SPEED;
+    /// Moves the level, peak and average of each bin towards the new levels
+    /// in dBFS
+    fn apply_ballistics(&mut self, vertices: &[FrequencyVertex], dt: Duration) {
+        // The number of bins changes with the FFT size and the scale
+        self.levels.resize(vertices.len(), MIN_DBFS);
+        self.peaks.resize(vertices.len(), MIN_DBFS);
+        self.peak_ages.resize(vertices.len(), Duration::ZERO);
+        self.powers.resize(vertices.len(), 0f32);
+
+        let ballistics = self.ballistics;
+        let attack = smoothing_factor(ballistics.attack, dt);
+        let release = smoothing_factor(ballistics.release, dt);
+        let average = smoothing_factor(ballistics.average, dt);
+
+        for (i, vertex) in vertices.iter().enumerate() {
+            let volume = vertex.volume;
+            if volume.is_nan() {
+                continue;
+            }
+
+            let factor = if volume > self.levels[i] {
+                attack
+            } else {
+                release
+            };
+            self.levels[i] += (volume - self.levels[i]) * factor;
+
+            if volume >= self.peaks[i] {
+                self.peaks[i] = volume;
+                self.peak_ages[i] = Duration::ZERO;
+            } else {
+                self.peak_ages[i] += dt;
+                if self.peak_ages[i] > ballistics.hold {
+                    self.peaks[i] = (self.peaks[i]
+                        - ballistics.decay * dt.as_secs_f32())
+                    .max(volume);
+                }
             }
-            vertex.volume = self.smoothing_buffer[i];
+
+            let power = 10f32.powf(volume / 10.);
+            self.powers[i] += (power - self.powers[i]) * average;
         }
     }
 
@@ -111,8 +272,8 @@ impl Spectrometer {
             0,
             vertices
                 .first()
-                .unwrap_or(&FrequencyVertex::empty())
-                .clone(),
+                .cloned()
+                .unwrap_or(FrequencyVertex::empty()),
         );
         fb.push(FrequencyVertex::empty());
 
@@ -123,19 +284,20 @@ impl Spectrometer {
                 let y2 = fb[i + 2].volume;
                 let y3 = fb[i + 3].volume;
 
-                let start =
-                    (fb[i + 1].position * interpolated.len() as f32) as usize;
-                let end =
-                    (fb[i + 2].position * interpolated.len() as f32) as usize;
+                let p1 = fb[i + 1].position * self.resolution as f32;
+                let p2 = fb[i + 2].position * self.resolution as f32;
+
+                // Only the part of the segment that is on screen
+                if p1 < self.resolution as f32 && p2 >= 0. {
+                    let start = p1.max(0.) as usize;
+                    let end = (p2 as usize).min(self.resolution - 1);
 
-                if start < self.resolution && end < self.resolution {
                     for j in start..=end {
-                        let pos: usize = j - start;
-                        let gap_size = end - start;
-                        let mut percentage: f32 = pos as f32 / gap_size as f32;
-                        if percentage.is_nan() {
-                            percentage = 0.5
-                        }
+                        let percentage = if p2 > p1 {
+                            ((j as f32 - p1) / (p2 - p1)).clamp(0., 1.)
+                        } else {
+                            0.5
+                        };
 
                         let t = percentage;
                         let t2 = percentage.powi(2);
@@ -172,3 +334,13 @@ impl Spectrometer {
         interpolated
     }
 }
+
+/// Fraction of the distance to the target covered in `dt` by an exponential
+/// with time constant `time`
+fn smoothing_factor(time: Duration, dt: Duration) -> f32 {
+    if time.is_zero() {
+        1.
+    } else {
+        1. - (-dt.as_secs_f32() / time.as_secs_f32()).exp()
+    }
+}
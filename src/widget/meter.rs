@@ -0,0 +1,194 @@
+use crate::player::ChannelLevels;
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse::{self, Cursor};
+use iced::widget::canvas::{
+    self, event, Event, Frame, Geometry, Path, Stroke, Text,
+};
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme};
+
+/// Lowest level shown in dBFS
+const FLOOR: f32 = -60.;
+
+/// Highest level shown in dBFS, leaving room for true peaks over full scale
+const CEILING: f32 = 3.;
+
+/// Levels with a gridline and label
+const MARKS: [f32; 7] = [0., -6., -12., -18., -24., -36., -48.];
+
+const LABEL_WIDTH: f32 = 24.;
+const LABEL_SIZE: f32 = 10.;
+const CLIP_HEIGHT: f32 = 16.;
+const READOUT_HEIGHT: f32 = 16.;
+const SPACING: f32 = 3.;
+
+/// Vertical bars of the level meters of each channel. The RMS level fills
+/// the bar, the sample and true peaks are lines across it and the peak hold
+/// is a number above it. The clip indicator at the top stays lit until the
+/// meter is clicked.
+pub struct LevelMeter<'a, Message> {
+    levels: &'a [ChannelLevels],
+    /// Which clip indicators are lit
+    clipped: &'a [bool],
+    on_reset: Message,
+}
+
+impl<'a, Message> LevelMeter<'a, Message> {
+    pub fn new(
+        levels: &'a [ChannelLevels],
+        clipped: &'a [bool],
+        on_reset: Message,
+    ) -> Self {
+        Self {
+            levels,
+            clipped,
+            on_reset,
+        }
+    }
+}
+
+impl<'a, Message: Clone> canvas::Program<Message> for LevelMeter<'a, Message> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (event::Status, Option<Message>) {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if cursor.is_over(bounds) =>
+            {
+                (event::Status::Captured, Some(self.on_reset.clone()))
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        let size = frame.size();
+
+        let top = CLIP_HEIGHT + READOUT_HEIGHT + SPACING * 2.;
+        let height = size.height - top;
+        let to_y = |level: f32| {
+            let position = (level - FLOOR) / (CEILING - FLOOR);
+            top + (1. - position.clamp(0., 1.)) * height
+        };
+
+        let label_color = Color {
+            a: 0.6,
+            ..palette.text
+        };
+        for level in MARKS {
+            let y = to_y(level);
+            frame.fill_text(Text {
+                content: format!("{}", level),
+                position: Point::new(LABEL_WIDTH - SPACING, y),
+                color: label_color,
+                size: LABEL_SIZE.into(),
+                horizontal_alignment: Horizontal::Right,
+                vertical_alignment: Vertical::Center,
+                ..Text::default()
+            });
+        }
+
+        let channels = self.levels.len().max(1);
+        let width = (size.width - LABEL_WIDTH) / channels as f32 - SPACING;
+        for (i, levels) in self.levels.iter().enumerate() {
+            let x = LABEL_WIDTH + i as f32 * (width + SPACING);
+
+            let clip_color = if self.clipped.get(i).copied().unwrap_or(false) {
+                palette.danger
+            } else {
+                Color {
+                    a: 0.1,
+                    ..palette.text
+                }
+            };
+            frame.fill_rectangle(
+                Point::new(x, 0.),
+                Size::new(width, CLIP_HEIGHT),
+                clip_color,
+            );
+
+            frame.fill_text(Text {
+                content: if levels.peak_hold > FLOOR {
+                    format!("{:.1}", levels.peak_hold)
+                } else {
+                    "-inf".to_string()
+                },
+                position: Point::new(
+                    x + width / 2.,
+                    CLIP_HEIGHT + SPACING + READOUT_HEIGHT / 2.,
+                ),
+                color: if levels.peak_hold > 0. {
+                    palette.danger
+                } else {
+                    palette.text
+                },
+                size: LABEL_SIZE.into(),
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                ..Text::default()
+            });
+
+            frame.fill_rectangle(
+                Point::new(x, top),
+                Size::new(width, height),
+                Color {
+                    a: 0.1,
+                    ..palette.text
+                },
+            );
+            let rms = to_y(levels.rms);
+            frame.fill_rectangle(
+                Point::new(x, rms),
+                Size::new(width, top + height - rms),
+                palette.primary,
+            );
+
+            let marker = |frame: &mut Frame, level: f32, color: Color| {
+                let y = to_y(level);
+                frame.stroke(
+                    &Path::line(Point::new(x, y), Point::new(x + width, y)),
+                    Stroke::default().with_color(color).with_width(2.),
+                );
+            };
+            marker(&mut frame, levels.peak, palette.text);
+            marker(
+                &mut frame,
+                levels.true_peak,
+                if levels.true_peak > 0. {
+                    palette.danger
+                } else {
+                    palette.success
+                },
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
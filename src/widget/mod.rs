@@ -1,3 +1,5 @@
 mod button;
+mod meter;
 
 pub use button::*;
+pub use meter::*;
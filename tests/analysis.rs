@@ -0,0 +1,49 @@
+use iced_audio_player::player::analysis::Analyzer;
+use iced_audio_player::player::{AnalysisConfig, FftSpectrum, WindowFunction};
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// Tolerance of the calibration in dB
+const TOLERANCE: f32 = 0.05;
+
+#[test]
+fn full_scale_sine_reads_0_dbfs() {
+    // Centered on bin 10 of the smallest FFT, and on a bin of every larger
+    // one
+    let bin = 10;
+    let frequency = (bin * SAMPLE_RATE as usize) as f32
+        / AnalysisConfig::FFT_SIZES[0] as f32;
+
+    for fft_size in AnalysisConfig::FFT_SIZES {
+        for window in WindowFunction::ALL {
+            let config = AnalysisConfig {
+                fft_size,
+                window,
+                ..AnalysisConfig::default()
+            };
+            let sine = (0..fft_size)
+                .map(|frame| {
+                    let phase = frame as f32 * frequency / SAMPLE_RATE as f32;
+                    (phase * std::f32::consts::TAU).sin()
+                })
+                .collect::<Vec<_>>();
+
+            let mut analyzer = Analyzer::new(config, 1);
+            let mut spectrum = FftSpectrum::empty();
+            analyzer.push(&sine, &[]);
+            analyzer.process(SAMPLE_RATE, &mut spectrum);
+
+            let levels = spectrum.left();
+            let peak = levels.iter().copied().fold(f32::MIN, f32::max);
+            let level =
+                levels[(frequency / spectrum.bin_size).round() as usize];
+            assert!(
+                level.abs() < TOLERANCE && level == peak,
+                "{:?} at {}: {} dBFS",
+                window,
+                fft_size,
+                level
+            );
+        }
+    }
+}
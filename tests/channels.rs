@@ -0,0 +1,66 @@
+use iced_audio_player::player::channels::ChannelMixer;
+
+const EPSILON: f32 = 1e-6;
+
+/// -3 dB, the ITU-R BS.775 weight for center and surround channels
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Mixes a single frame
+fn mix(mixer: &ChannelMixer, input: &[f32], outputs: usize) -> Vec<f32> {
+    let mut output = vec![0f32; outputs];
+    assert_eq!(mixer.mix(input, &mut output), outputs);
+    output
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!((actual - expected).abs() < EPSILON, "{:?}", actual);
+    }
+}
+
+#[test]
+fn passes_matching_channels_through() {
+    for channels in 1..=8 {
+        assert!(ChannelMixer::new(channels, channels, channels).is_none());
+    }
+}
+
+#[test]
+fn mono_goes_to_both_sides() {
+    let mixer = ChannelMixer::new(1, 1, 2).unwrap();
+    assert_close(&mix(&mixer, &[0.5], 2), &[0.5, 0.5]);
+
+    let mixer = ChannelMixer::new(1, 2, 2).unwrap();
+    assert_close(&mix(&mixer, &[0.5], 2), &[0.5, 0.5]);
+}
+
+#[test]
+fn stereo_is_averaged_to_mono() {
+    let mixer = ChannelMixer::new(2, 1, 1).unwrap();
+    assert_close(&mix(&mixer, &[1., 0.5], 1), &[0.75]);
+
+    // Played as mono on a stereo device
+    let mixer = ChannelMixer::new(2, 1, 2).unwrap();
+    assert_close(&mix(&mixer, &[1., 0.5], 2), &[0.75, 0.75]);
+}
+
+#[test]
+fn surround_is_downmixed_to_stereo() {
+    let mixer = ChannelMixer::new(6, 2, 2).unwrap();
+    // L R C LFE Ls Rs, one channel at a time
+    let expected = [
+        [1., 0.],
+        [0., 1.],
+        [MINUS_3DB, MINUS_3DB],
+        [0., 0.],
+        [MINUS_3DB, 0.],
+        [0., MINUS_3DB],
+    ];
+
+    for (channel, expected) in expected.iter().enumerate() {
+        let mut input = [0f32; 6];
+        input[channel] = 1.;
+        assert_close(&mix(&mixer, &input, 2), expected);
+    }
+}
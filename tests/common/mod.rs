@@ -0,0 +1,28 @@
+use hound::{Sample, WavSpec, WavWriter};
+use std::path::PathBuf;
+
+/// Path in the temporary directory for a fixture, unique per test process
+pub fn fixture_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "iced-audio-player-{}-{}.wav",
+        std::process::id(),
+        name
+    ))
+}
+
+/// Writes interleaved `samples` in the format of `spec` to a new fixture
+pub fn write_fixture<S: Sample>(
+    name: &str,
+    spec: WavSpec,
+    samples: impl IntoIterator<Item = S>,
+) -> PathBuf {
+    let path = fixture_path(name);
+
+    let mut writer = WavWriter::create(&path, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    path
+}
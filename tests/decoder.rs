@@ -0,0 +1,127 @@
+mod common;
+
+use common::{fixture_path, write_fixture};
+use hound::{SampleFormat, WavSpec};
+use iced_audio_player::player::decoder::Decoder;
+use std::path::{Path, PathBuf};
+
+const EPSILON: f32 = 1e-6;
+
+/// Full scale sequence every fixture encodes: 0, +0.5, -0.5, +max, -1
+const EXPECTED: [f32; 5] = [0., 0.5, -0.5, 1., -1.];
+
+fn write_int_fixture(name: &str, bits: u16, channels: u16) -> PathBuf {
+    let spec = WavSpec {
+        channels,
+        sample_rate: 44100,
+        bits_per_sample: bits,
+        sample_format: SampleFormat::Int,
+    };
+    let max = (1i64 << (bits - 1)) as f32;
+    let samples = EXPECTED.iter().flat_map(|value| {
+        let sample = (value * max).clamp(-max, max - 1.) as i32;
+        std::iter::repeat_n(sample, channels as usize)
+    });
+
+    if bits == 8 {
+        write_fixture(name, spec, samples.map(|sample| sample as i8))
+    } else {
+        write_fixture(name, spec, samples)
+    }
+}
+
+fn write_float_fixture(name: &str) -> PathBuf {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+
+    write_fixture(name, spec, EXPECTED)
+}
+
+fn decode_all(path: &Path) -> Vec<f32> {
+    let mut decoder = Decoder::open(path).unwrap();
+    let mut buffer = vec![0f32; 64];
+    let read = decoder.read(&mut buffer).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    buffer.truncate(read);
+    buffer
+}
+
+fn assert_decoded(decoded: &[f32], bits: u16) {
+    assert_eq!(decoded.len(), EXPECTED.len());
+
+    // The positive full scale value is one step below 1.0
+    let step = 1. / (1i64 << (bits - 1)) as f32;
+    for (sample, expected) in decoded.iter().zip(EXPECTED) {
+        assert!(
+            (sample - expected).abs() <= step + EPSILON,
+            "decoded {} for {} at {} bits",
+            sample,
+            expected,
+            bits
+        );
+    }
+}
+
+#[test]
+fn decodes_int_formats() {
+    for bits in [8, 16, 24, 32] {
+        let path = write_int_fixture(&format!("int{}", bits), bits, 1);
+        assert_decoded(&decode_all(&path), bits);
+    }
+}
+
+#[test]
+fn decodes_float_format() {
+    let path = write_float_fixture("float32");
+    let decoded = decode_all(&path);
+
+    assert_eq!(decoded, EXPECTED);
+}
+
+#[test]
+fn decodes_interleaved_channels() {
+    let path = write_int_fixture("int24-stereo", 24, 2);
+    let decoded = decode_all(&path);
+
+    let left = decoded.iter().step_by(2).copied().collect::<Vec<_>>();
+    let right = decoded
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .copied()
+        .collect::<Vec<_>>();
+    assert_decoded(&left, 24);
+    assert_eq!(left, right);
+}
+
+#[test]
+fn seeks_to_frame() {
+    let path = write_int_fixture("int16-seek", 16, 2);
+    let mut decoder = Decoder::open(&path).unwrap();
+    assert_eq!(decoder.duration(), EXPECTED.len() as u32);
+
+    decoder.seek(3).unwrap();
+    let mut buffer = [0f32; 8];
+    let read = decoder.read(&mut buffer).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(read, 4);
+    assert!((buffer[0] - EXPECTED[3]).abs() <= 1. / 32768. + EPSILON);
+    assert_eq!(buffer[1], buffer[0]);
+    assert_eq!(&buffer[2..4], &[EXPECTED[4]; 2]);
+}
+
+#[test]
+fn rejects_non_wav_files() {
+    let path = fixture_path("corrupt");
+    std::fs::write(&path, b"definitely not a wav file").unwrap();
+    let result = Decoder::open(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::write_fixture;
+use hound::{SampleFormat, WavSpec};
+use iced_audio_player::player::loudness::{scan, LoudnessReport, ReportFormat};
+use iced_audio_player::player::NormalizationConfig;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// Tolerance of the EBU Tech 3341 compliance tests in LU
+const TOLERANCE: f32 = 0.1;
+
+/// Writes 1 kHz sines, one `(level in dBFS, seconds)` after another, to
+/// every channel
+fn write_sine_fixture(
+    name: &str,
+    channels: u16,
+    parts: &[(f32, f32)],
+) -> PathBuf {
+    let spec = WavSpec {
+        channels,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+
+    let mut samples = vec![];
+    let mut frame = 0;
+    for (level, seconds) in parts {
+        let amplitude = 10f32.powf(level / 20.);
+        for _ in 0..(seconds * SAMPLE_RATE as f32) as usize {
+            let phase = 2. * PI * 1000. * frame as f32 / SAMPLE_RATE as f32;
+            for _ in 0..channels {
+                samples.push(amplitude * phase.sin());
+            }
+            frame += 1;
+        }
+    }
+
+    write_fixture(name, spec, samples)
+}
+
+#[test]
+fn stereo_sine_reads_its_level() {
+    // A stereo 1 kHz sine at -23 dBFS measures -23 LUFS
+    let path = write_sine_fixture("loudness-sine", 2, &[(-23., 20.)]);
+    let report = scan(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!((report.integrated + 23.).abs() <= TOLERANCE, "{:?}", report);
+    assert!((report.max_momentary + 23.).abs() <= TOLERANCE);
+    assert!((report.max_short_term + 23.).abs() <= TOLERANCE);
+    assert!(report.range.abs() <= TOLERANCE);
+    assert!((report.true_peak + 23.).abs() <= TOLERANCE, "{:?}", report);
+    assert!((report.duration - 20.).abs() < 1e-6);
+}
+
+#[test]
+fn gates_quiet_parts() {
+    // EBU Tech 3341 test case 3: the -36 dBFS parts fall below the relative
+    // gate and the integrated loudness is -23 LUFS
+    let path = write_sine_fixture(
+        "loudness-gated",
+        2,
+        &[(-36., 10.), (-23., 60.), (-36., 10.)],
+    );
+    let report = scan(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!((report.integrated + 23.).abs() <= TOLERANCE, "{:?}", report);
+}
+
+#[test]
+fn measures_loudness_range() {
+    // EBU Tech 3342 test case 1: 20 s at -20 dBFS and 20 s at -30 dBFS have
+    // a loudness range of 10 LU
+    let path =
+        write_sine_fixture("loudness-range", 2, &[(-20., 20.), (-30., 20.)]);
+    let report = scan(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!((report.range - 10.).abs() <= TOLERANCE * 2., "{:?}", report);
+}
+
+#[test]
+fn exports_reports_next_to_the_file() {
+    let path = write_sine_fixture("loudness-export", 1, &[(-20., 5.)]);
+    let report = scan(&path).unwrap();
+
+    let text = report.export(ReportFormat::Text).unwrap();
+    let json = report.export(ReportFormat::Json).unwrap();
+    let text_contents = std::fs::read_to_string(&text).unwrap();
+    let json_contents = std::fs::read_to_string(&json).unwrap();
+    for file in [&path, &text, &json] {
+        std::fs::remove_file(file).unwrap();
+    }
+
+    assert_eq!(text, path.with_extension("loudness.txt"));
+    assert_eq!(json, path.with_extension("loudness.json"));
+    assert!(text_contents.contains("Integrated loudness: -23.0 LUFS"));
+    assert!(json_contents.contains("\"integrated\": -23.0"));
+}
+
+#[test]
+fn normalization_keeps_true_peaks_below_the_ceiling() {
+    let report = |integrated: f32, true_peak: f32| LoudnessReport {
+        path: PathBuf::new(),
+        duration: 10.,
+        integrated,
+        range: 0.,
+        max_momentary: integrated,
+        max_short_term: integrated,
+        true_peak,
+    };
+    let config = NormalizationConfig {
+        enabled: true,
+        target: -14.,
+    };
+
+    assert_eq!(config.gain(&report(-23., -20.)), 9.);
+    // Raising it by 9 dB would take its true peak over -1 dBTP
+    assert_eq!(config.gain(&report(-23., -5.)), 4.);
+    assert_eq!(config.gain(&report(-8., 0.)), -6.);
+    assert_eq!(
+        config.gain(&report(f32::NEG_INFINITY, f32::NEG_INFINITY)),
+        0.
+    );
+}
@@ -0,0 +1,267 @@
+mod common;
+
+use common::{fixture_path, write_fixture};
+use futures::FutureExt;
+use hound::WavReader;
+use hound::{SampleFormat, WavSpec};
+use iced_audio_player::player::backend::{NullBackend, WavBackend};
+use iced_audio_player::player::{
+    ChannelMode, Player, PlayerEvent, PlayerEvents,
+};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// How long playback may take to get somewhere on a busy machine
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Writes `seconds` of a quiet stereo sine
+fn write_sine_fixture(name: &str, seconds: f32) -> PathBuf {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let samples =
+        (0..(seconds * SAMPLE_RATE as f32) as usize).flat_map(|frame| {
+            let phase = frame as f32 * 440. / SAMPLE_RATE as f32;
+            let sample = ((phase * std::f32::consts::TAU).sin() * 1000.) as i16;
+            [sample, sample]
+        });
+
+    write_fixture(name, spec, samples)
+}
+
+/// Events sent so far, without waiting for more
+fn take_events(events: &PlayerEvents) -> Vec<PlayerEvent> {
+    let mut received = vec![];
+    while let Some(Some(event)) = events.next().now_or_never() {
+        received.push(event);
+    }
+    received
+}
+
+/// Polls `done` until it returns true, or gives up after `TIMEOUT`
+fn wait_until(mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+fn track_ends(events: &[PlayerEvent]) -> usize {
+    events
+        .iter()
+        .filter(|event| matches!(event, PlayerEvent::TrackEnded))
+        .count()
+}
+
+#[test]
+fn seeking_mid_file_keeps_playing() {
+    let path = write_sine_fixture("playback-seek", 20.);
+    let mut player = Player::new(Box::new(NullBackend));
+    let events = player.events();
+
+    player.load_file(path.clone()).unwrap();
+    let started = wait_until(|| player.get_position() > 0.1);
+    player.set_position(10.);
+    let mut received = vec![];
+    let resumed = wait_until(|| {
+        received.extend(take_events(&events));
+        player.get_position() > 10.1 || track_ends(&received) > 0
+    });
+
+    let playing = player.is_playing();
+    player.unload();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(started && resumed);
+    assert_eq!(track_ends(&received), 0, "{:?}", received);
+    assert!(playing);
+}
+
+#[test]
+fn wav_backend_records_the_file() {
+    let path = write_sine_fixture("playback-record", 0.5);
+    let output = fixture_path("playback-record-output");
+    let mut player = Player::new(Box::new(WavBackend::new(&output)));
+    let events = player.events();
+
+    player.load_file(path.clone()).unwrap();
+    let mut received = vec![];
+    let ended = wait_until(|| {
+        received.extend(take_events(&events));
+        track_ends(&received) > 0
+    });
+    player.unload();
+
+    let decoded = WavReader::open(&path)
+        .unwrap()
+        .samples::<i16>()
+        .map(|sample| sample.unwrap() as f32 / 32768.)
+        .collect::<Vec<_>>();
+    let recorded = WavReader::open(&output)
+        .unwrap()
+        .samples::<f32>()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&output).unwrap();
+
+    // The output plays silence before the first samples are decoded, after
+    // the end of the file and on underruns, so only the rest is compared
+    let audible = |samples: Vec<f32>| {
+        samples
+            .into_iter()
+            .filter(|sample| *sample != 0.)
+            .collect::<Vec<_>>()
+    };
+    assert!(ended);
+    assert_eq!(audible(recorded), audible(decoded));
+}
+
+#[test]
+fn reopening_records_a_valid_file() {
+    let path = write_sine_fixture("playback-reopen", 5.);
+    let output = fixture_path("playback-reopen-output");
+    let mut player = Player::new(Box::new(WavBackend::new(&output)));
+
+    player.load_file(path.clone()).unwrap();
+    let started = wait_until(|| player.get_position() > 0.1);
+    // Rebuilds the stream, which records to the same file again
+    player.set_channel_mode(ChannelMode::Mono).unwrap();
+    let resumed = wait_until(|| player.get_position() > 0.2);
+    player.unload();
+
+    let mut reader = WavReader::open(&output).unwrap();
+    let channels = reader.spec().channels;
+    let len = reader.len();
+    let samples = reader.samples::<f32>().count();
+    // A stream writing on after the file was recreated leaves its samples
+    // behind the end the header declares
+    let bytes = std::fs::read(&output).unwrap();
+    let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&output).unwrap();
+
+    assert!(started && resumed);
+    assert_eq!(channels, 1);
+    assert!(len > 0);
+    assert_eq!(samples, len as usize);
+    assert_eq!(riff_len as usize + 8, bytes.len());
+}
+
+#[test]
+fn position_follows_a_seek() {
+    let path = write_sine_fixture("playback-position", 20.);
+    let mut player = Player::new(Box::new(NullBackend));
+    let events = player.events();
+
+    player.load_file(path.clone()).unwrap();
+    player.set_position(10.);
+    let seeked = player.get_position();
+    let mut position = seeked;
+    let resumed = wait_until(|| {
+        position = player.get_position();
+        position > 10.1
+    });
+
+    let received = take_events(&events);
+    player.unload();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(seeked, 10.);
+    // Playback goes on from the new position in real time
+    assert!(resumed);
+    assert!(position < 11., "{}", position);
+    assert!(received.iter().any(|event| matches!(
+        event,
+        PlayerEvent::PositionChanged { seconds } if *seconds == 10.
+    )));
+}
+
+#[test]
+fn end_of_file_is_reported_once() {
+    let path = write_sine_fixture("playback-end", 0.5);
+    let mut player = Player::new(Box::new(NullBackend));
+    let events = player.events();
+
+    player.load_file(path.clone()).unwrap();
+    let mut received = vec![];
+    let ended = wait_until(|| {
+        received.extend(take_events(&events));
+        track_ends(&received) > 0
+    });
+    // Any second report would follow while the output keeps running
+    thread::sleep(Duration::from_millis(300));
+    received.extend(take_events(&events));
+
+    let position = player.get_position();
+    player.unload();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(ended);
+    assert_eq!(track_ends(&received), 1, "{:?}", received);
+    assert_eq!(position, 0.5);
+}
+
+#[test]
+fn channel_mode_is_kept_per_file() {
+    let first = write_sine_fixture("playback-mode-first", 1.);
+    let second = write_sine_fixture("playback-mode-second", 1.);
+    let mut player = Player::new(Box::new(NullBackend));
+
+    player.load_file(first.clone()).unwrap();
+    player.set_channel_mode(ChannelMode::Mono).unwrap();
+    player.load_file(second.clone()).unwrap();
+    let second_mode = player.get_channel_mode();
+    player.load_file(first.clone()).unwrap();
+    let first_mode = player.get_channel_mode();
+    player.unload();
+    std::fs::remove_file(&first).unwrap();
+    std::fs::remove_file(&second).unwrap();
+
+    assert_eq!(second_mode, ChannelMode::Auto);
+    assert_eq!(first_mode, ChannelMode::Mono);
+}
+
+#[test]
+fn clipping_is_counted_on_both_sides() {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let samples = (0..SAMPLE_RATE / 4).map(|frame| match frame {
+        1000 => i16::MAX,
+        2000 => i16::MIN,
+        _ => 0,
+    });
+    let path = write_fixture("playback-clipping", spec, samples);
+
+    let mut player = Player::new(Box::new(NullBackend));
+    player.load_file(path.clone()).unwrap();
+    let mut clips = 0;
+    wait_until(|| {
+        clips = player
+            .get_levels()
+            .channels
+            .first()
+            .map_or(0, |channel| channel.clips);
+        clips >= 2
+    });
+    player.unload();
+    std::fs::remove_file(&path).unwrap();
+
+    // The largest positive 16-bit sample decodes to just below 1
+    assert_eq!(clips, 2);
+}
@@ -0,0 +1,91 @@
+use iced_audio_player::player::resampler::{ResampleQuality, Resampler};
+
+const FROM_RATE: u32 = 44100;
+const TO_RATE: u32 = 48000;
+
+const QUALITIES: [ResampleQuality; 3] = [
+    ResampleQuality::Low,
+    ResampleQuality::Medium,
+    ResampleQuality::High,
+];
+
+/// Resamples all of mono `input` from `FROM_RATE` to `TO_RATE`
+fn resample(quality: ResampleQuality, input: &[f32]) -> Vec<f32> {
+    let mut resampler = Resampler::new(quality, FROM_RATE, TO_RATE, 1).unwrap();
+    let mut position = 0;
+    let mut output = vec![];
+    let mut buffer = vec![0f32; 1000];
+
+    loop {
+        let written = resampler
+            .read(&mut buffer, |samples| {
+                let len = samples.len().min(input.len() - position);
+                samples[..len].copy_from_slice(&input[position..][..len]);
+                position += len;
+                Ok(len)
+            })
+            .unwrap();
+        output.extend_from_slice(&buffer[..written]);
+        if written < buffer.len() {
+            return output;
+        }
+    }
+}
+
+/// Input with a single full scale sample at `frame`
+fn impulse(len: usize, frame: usize) -> Vec<f32> {
+    let mut input = vec![0f32; len];
+    input[frame] = 1.;
+    input
+}
+
+fn loudest_frame(output: &[f32]) -> usize {
+    (0..output.len())
+        .max_by(|&a, &b| output[a].abs().total_cmp(&output[b].abs()))
+        .unwrap()
+}
+
+fn expected_frame(input_frame: usize) -> usize {
+    (input_frame as f64 * TO_RATE as f64 / FROM_RATE as f64).round() as usize
+}
+
+#[test]
+fn output_length_follows_the_rate_ratio() {
+    for quality in QUALITIES {
+        let output = resample(quality, &vec![0.5; FROM_RATE as usize]);
+        assert_eq!(output.len(), TO_RATE as usize, "{:?}", quality);
+    }
+}
+
+#[test]
+fn output_delay_is_skipped() {
+    for quality in QUALITIES {
+        let output = resample(quality, &impulse(10000, 1000));
+        let frame = loudest_frame(&output);
+        assert!(
+            frame.abs_diff(expected_frame(1000)) <= 1,
+            "{:?} at {}",
+            quality,
+            frame
+        );
+    }
+}
+
+#[test]
+fn tail_is_flushed_at_the_end() {
+    // The input ends just short of a whole resampler pass, so less padding
+    // than the resampler delay is left in the last one
+    for len in [1023, 2047, 4000] {
+        for quality in QUALITIES {
+            let output = resample(quality, &impulse(len, len - 2));
+            let frame = loudest_frame(&output);
+            assert_eq!(output.len(), expected_frame(len), "{:?}", quality);
+            assert!(
+                frame.abs_diff(expected_frame(len - 2)) <= 1,
+                "{:?} at {}",
+                quality,
+                frame
+            );
+        }
+    }
+}